@@ -0,0 +1,75 @@
+use std::io;
+
+pub const USAGE: &str = "\
+Usage: ostruka [OPTIONS]
+       ostruka hash-password
+
+Options:
+    -c, --config <path>    Read the configuration from the given file
+    -p, --profile <name>   Use the given profile of the config file
+    -s, --server <addr>    Server address as host:port, overrides the config
+    -u, --user <alias>     Alias to log in with, overrides the config
+    -h, --help             Print this help and exit
+    -V, --version          Print version information and exit
+
+Commands:
+    hash-password          Read a password and print its SHA-1 digest, as used
+                           in the password field of the config file";
+
+/// Options given in the command line. All of them are optional, the ones that are
+/// given override the values read from the config file.
+#[derive(Default)]
+pub struct Args {
+    pub config: Option<String>,
+    pub profile: Option<String>,
+    pub server: Option<String>,
+    pub user: Option<String>,
+}
+
+/// What the user asked ostruka to do from the command line.
+pub enum Action {
+    Run(Args),
+    HashPassword,
+    Help,
+    Version,
+}
+
+/// Parses the given command line arguments. The first item of the iterator must be
+/// the name of the program, as returned by `std::env::args()`.
+pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Action, io::Error> {
+    // Skip the program's name
+    let _ = args.next();
+
+    let mut parsed = Args::default();
+
+    while let Some(arg) = args.next() {
+        // Options can be given as `--opt value` or as `--opt=value`
+        let (flag, inline_value) = match arg.find('=') {
+            Some(i) if arg.starts_with("--") => (arg[..i].to_string(), Some(arg[i+1..].to_string())),
+            _ => (arg.clone(), None),
+        };
+
+        let slot = match flag.as_str() {
+            "hash-password" => return Ok(Action::HashPassword),
+            "-h" | "--help" => return Ok(Action::Help),
+            "-V" | "--version" => return Ok(Action::Version),
+            "-c" | "--config" => &mut parsed.config,
+            "-p" | "--profile" => &mut parsed.profile,
+            "-s" | "--server" => &mut parsed.server,
+            "-u" | "--user" => &mut parsed.user,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("Unknown argument: {}", arg))),
+        };
+
+        // Get the value of the option
+        let value = match inline_value.or_else(|| args.next()) {
+            Some(v) if !v.is_empty() => v,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("Missing value for {}", flag))),
+        };
+
+        *slot = Some(value);
+    }
+
+    Ok(Action::Run(parsed))
+}
@@ -12,12 +12,15 @@ extern crate sha1;
 // use sha1::*;
 
 use crate::instance::{Instance, Page};
+use crate::line::ChatLine;
+use crate::notify::NotifyLevel;
+use crate::parser;
 
 use ostrich_core::*;
 
 use crate::Tx;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum UserCommand {
     Message(String),    // Contains a message to send
     ChangePage(usize),  // Change the current page to the specified
@@ -26,45 +29,20 @@ pub enum UserCommand {
     ScrollUp,
     ScrollDown,
     Close,              // Close the current page
+    Queue(QueueAction), // Manage the messages waiting to be sent
+    ToggleTimestamps,   // Show or hide the time of each line
+    Notify(Option<NotifyLevel>), // Show or set the notification level of the current page
+    Help(Option<String>), // Show the commands, or the usage of the given one
     Exit,               // exit ostruka
     Unknown(String),
 }
 
-pub fn parse_command(input: &str) -> UserCommand {
-    // let input = input.to_lowercase(); 
-
-    // Exit command
-    if input.starts_with(":exit") {
-        UserCommand::Exit
-    
-    // Join command
-    } else if input.starts_with(":join") && input.len() > 5{
-        let mut join_name = input.to_string();
-        let _= join_name.drain(..6); // 6, includes whitespace
-
-        UserCommand::Join(join_name)
-    
-    // Close command
-    } else if input == ":q" || input == ":close" {
-        UserCommand::Close
-
-    } else if input.starts_with(':') {
-
-        let no_start = input.trim_matches(':');
-        
-        // ChangePage commad
-        if let Ok(num) = no_start.parse() {
-            return UserCommand::ChangePage(num);
-        }
-
-        // Unknown command
-        UserCommand::Unknown(input.to_string())
-
-    // Message command 
-    } else {
-        UserCommand::Message(input.to_string())
-    }
-} 
+#[derive(Debug, PartialEq)]
+pub enum QueueAction {
+    List,                 // Show the queued messages of the current page
+    Drop(Option<usize>),  // Drop the given message, or all of them
+    Edit(usize, String),  // Replace the text of the given message
+}
 
 pub async fn run_command<B: Backend>(username: &str,
                                      command: &UserCommand,
@@ -92,14 +70,15 @@ pub async fn run_command<B: Backend>(username: &str,
                                            target,
                                            ms.to_string());
 
-                // Create a Command to send to the client
+                // Create a Command to send to the client. If it cannot be sent,
+                // the client is disconnected, so queue the message until reconnected.
                 if let Err(_) = client_tx.send(command) {
-                    return Err(io::Error::new(io::ErrorKind::ConnectionAborted, 
-                                              "Client died, nothing to do"));
+                    instance.lock().await.queue_msg(username, ms)?;
+                    return Ok(());
                 }
                 // Display message in the Page
                 instance.lock().await
-                    .add_line(None, &format!("({})> {}", username, ms))?;
+                    .push_line(None, ChatLine::own(username, ms))?;
             }
         },
 
@@ -112,28 +91,7 @@ pub async fn run_command<B: Backend>(username: &str,
             }
         },
 
-        UserCommand::Join(name) => {
-            // Send a Join command
-            if let Err(e) = client_tx.send(Command::Join(name.clone())) {
-                // Display the error in the current page
-                instance.lock().await.add_err(&format!("Join command error: {}", e)).unwrap();
-            } else {
-                // If the joined chat is a group, reques a list of online users
-                if name.starts_with('#') {
-                    // Sending a ListUsr command to the server requests a list of all users in a
-                    // group. The fields in this ListUsr commands are ignored by the server. 
-                    let cmd = Command::ListUsr(name.to_string(), ListUsrOperation::Add, String::new());
-                    if let Err(_) = client_tx.send(cmd) {
-                        return Err(io::Error::new(io::ErrorKind::ConnectionAborted, 
-                                                  "Client died, nothing to do"));
-                    }
-                }
-                // If send successful, add a new page
-                instance.lock().await.add(
-                    Page::new(name.clone(), vec![format!("Joined {}!", name)])
-                )?;
-            }
-        },
+        UserCommand::Join(name) => join(name, instance, client_tx).await?,
 
         UserCommand::ListUsr => {
             // Get current page's name
@@ -155,17 +113,69 @@ pub async fn run_command<B: Backend>(username: &str,
             // Remove the page the user is currently in
             instance.lock().await.remove_current()?;
             // Remove of the page was ok, now send a leave command 
-            // to the server in order to close the connection with the target
-            if let Err(_) = client_tx.send(Command::Leave(name)) {
-                return Err(io::Error::new(io::ErrorKind::ConnectionAborted, 
-                                          "Client died, nothing to do"));
+            // to the server in order to close the connection with the target.
+            // If it cannot be sent, the client is disconnected. Closed pages are not
+            // joined again after reconnecting, so there is nothing left to do.
+            if let Err(_) = client_tx.send(Command::Leave(name.clone())) {
+                instance.lock().await
+                    .add_line(Some(0), &format!("Not connected, the leave of {} was not sent", name))?;
             }
         },
         
+        UserCommand::ToggleTimestamps => {
+            let mut instance = instance.lock().await;
+            instance.show_timestamps = !instance.show_timestamps;
+        },
+
+        UserCommand::Notify(level) => {
+            let mut instance = instance.lock().await;
+            let name = instance.get_name();
+            if let Some(level) = level {
+                instance.notify_levels.insert(name.clone(), *level);
+            }
+            let current = instance.notify_level(&name);
+            instance.add_line(None, &format!("Notification level of {}: {:?}", name, current))?;
+        },
+
+        UserCommand::Queue(action) => {
+            let mut instance = instance.lock().await;
+            match action {
+                QueueAction::List => {
+                    let pending = instance.get_pending();
+                    if pending.is_empty() {
+                        instance.add_line(None, "No queued messages in this page")?;
+                    }
+                    for (i, msg) in pending.iter().enumerate() {
+                        instance.add_line(None, &format!("[queue {}]: {}", i, msg.text))?;
+                    }
+                },
+                QueueAction::Drop(index) => instance.drop_pending(*index)?,
+                QueueAction::Edit(index, txt) => instance.edit_pending(*index, txt)?,
+            }
+        },
+
+        // Show the help in the home page
+        UserCommand::Help(name) => {
+            let lines = match parser::help(name.as_deref()) {
+                Some(lines) => lines,
+                None => return Err(io::Error::new(io::ErrorKind::InvalidInput, 
+                                                  format!("Unknown command: {}, type :help \
+                                                          to list the commands", 
+                                                          name.as_deref().unwrap_or("")))),
+            };
+
+            let mut instance = instance.lock().await;
+            for line in lines {
+                instance.add_line(Some(0), &line)?;
+            }
+            instance.set_current(0)?;
+            instance.scroll_zero();
+        },
+
         // Display a warning in the current page about the unknown command
         UserCommand::Unknown(c) => {
             let _ = instance.lock().await
-                .add_err(&format!("Unknown command: {}", c))?;
+                .add_err(&format!("Unknown command: {}, type :help to list the commands", c))?;
         },
         
         // For ScrollDown and ScrollUp, that cannot be runned in this function
@@ -176,3 +186,82 @@ pub async fn run_command<B: Backend>(username: &str,
     }
     Ok(())
 }
+
+/// Joins the given chat: sends a Join command to the server and, if successful, adds a
+/// new `Page` for the chat. If the chat is a group, a list of online users is requested.
+pub async fn join(name: &str,
+                  instance: &Arc<Mutex<Instance>>,
+                  client_tx: &mut Tx) -> Result<(), io::Error> {
+    send_join(name, client_tx)?;
+
+    // If send successful, add a new page
+    instance.lock().await.add(
+        Page::new(name.to_string(), vec![ChatLine::presence(&format!("Joined {}!", name))])
+    )
+}
+
+/// Joins again every open chat after reconnecting to the server. The pages are kept as
+/// they are, a line is added to each of them to show that messages might have been missed.
+pub async fn rejoin(instance: &Arc<Mutex<Instance>>, client_tx: &mut Tx) -> Result<(), io::Error> {
+    let names = instance.lock().await.names();
+
+    // The first page is ostruka's home page, not a chat
+    for (index, name) in names.iter().enumerate().skip(1) {
+        // The online users are requested again, so the old list is discarded
+        instance.lock().await.clear_online_users(name);
+        send_join(name, client_tx)?;
+
+        instance.lock().await
+            .push_line(Some(index), 
+                       ChatLine::presence("--- reconnected, some messages might have been missed ---"))?;
+    }
+    Ok(())
+}
+
+/// Sends a Join command for the given chat. If the chat is a group, a ListUsr command
+/// is sent too, to request the list of online users.
+fn send_join(name: &str, client_tx: &mut Tx) -> Result<(), io::Error> {
+    // Send a Join command
+    if let Err(e) = client_tx.send(Command::Join(name.to_string())) {
+        return Err(io::Error::new(io::ErrorKind::ConnectionAborted,
+                                  format!("Join command error: {}", e)));
+    }
+
+    // If the joined chat is a group, reques a list of online users
+    if name.starts_with('#') {
+        // Sending a ListUsr command to the server requests a list of all users in a
+        // group. The fields in this ListUsr commands are ignored by the server. 
+        let cmd = Command::ListUsr(name.to_string(), ListUsrOperation::Add, String::new());
+        if let Err(_) = client_tx.send(cmd) {
+            return Err(io::Error::new(io::ErrorKind::ConnectionAborted, 
+                                      "Client died, nothing to do"));
+        }
+    }
+    Ok(())
+}
+
+/// Sends the messages queued while disconnected, by order. Returns the number of sent
+/// messages. If a message cannot be sent, it and the rest are queued again.
+pub async fn flush_queue(instance: &Arc<Mutex<Instance>>, client_tx: &mut Tx) -> Result<usize, io::Error> {
+    let mut instance = instance.lock().await;
+    let mut queued = instance.take_pending().into_iter();
+    let mut sent = 0;
+
+    // The messages are sent with the current alias, it might have changed on reconnection
+    let sender = instance.status.alias.clone();
+
+    while let Some((target, msg)) = queued.next() {
+        let command = Command::Msg(sender.clone(), target.clone(), msg.text.clone());
+
+        if let Err(_) = client_tx.send(command) {
+            instance.requeue(&target, msg);
+            queued.for_each(|(target, msg)| instance.requeue(&target, msg));
+            return Err(io::Error::new(io::ErrorKind::ConnectionAborted, 
+                                      "Client died, nothing to do"));
+        }
+
+        instance.mark_sent(&target, &msg);
+        sent += 1;
+    }
+    Ok(sent)
+}
@@ -0,0 +1,76 @@
+use crate::input::InputBuffer;
+use crate::parser;
+
+/// Completion of the word under the cursor. Pressing Tab again cycles through the
+/// candidates, replacing the word each time.
+pub struct Completion {
+    start: usize, // Byte index where the completed word starts
+    candidates: Vec<String>,
+    index: Option<usize>, // Candidate in the buffer, None before the first one is applied
+}
+
+impl Completion {
+
+    /// Looks for the candidates to complete the word under the cursor. The part of the
+    /// word before the cursor is matched, and the whole word is replaced. At the start
+    /// of the line, a word starting with `:` is completed with the command names. After
+    /// `:join`, it is completed with the given page names and nicknames. Elsewhere, it
+    /// is completed with the nicknames. Returns `None` if nothing matches.
+    pub fn new(buff: &InputBuffer, pages: &[String], nicks: &[String]) -> Option<Completion> {
+        let (before, _, _) = buff.split_at_cursor();
+        let start = buff.word_start();
+        let word = &before[start..];
+
+        let candidates = if start == 0 && word.starts_with(':') {
+            parser::command_names()
+                .into_iter()
+                .filter(|name| name.starts_with(word))
+                .collect()
+        } else if before[..start].trim() == ":join" {
+            matching(pages.iter().chain(nicks), word)
+        } else {
+            matching(nicks.iter(), word)
+        };
+
+        if candidates.is_empty() {
+            None
+        } else {
+            Some(Completion { start, candidates, index: None })
+        }
+    }
+
+    /// Replaces the word with the next candidate.
+    pub fn next(&mut self, buff: &mut InputBuffer) {
+        let index = match self.index {
+            Some(i) => (i + 1) % self.candidates.len(),
+            None => 0,
+        };
+        self.apply(index, buff);
+    }
+
+    /// Replaces the word with the previous candidate.
+    pub fn prev(&mut self, buff: &mut InputBuffer) {
+        let index = match self.index {
+            Some(i) if i > 0 => i - 1,
+            _ => self.candidates.len() - 1,
+        };
+        self.apply(index, buff);
+    }
+
+    fn apply(&mut self, index: usize, buff: &mut InputBuffer) {
+        self.index = Some(index);
+        buff.replace_word(self.start, &self.candidates[index]);
+    }
+}
+
+/// Returns the names that start with the given word, ignoring case and duplicates.
+fn matching<'a, I: Iterator<Item=&'a String>>(names: I, word: &str) -> Vec<String> {
+    let word = word.to_lowercase();
+    let mut found: Vec<String> = vec![];
+    for name in names {
+        if name.to_lowercase().starts_with(&word) && !found.contains(name) {
+            found.push(name.clone());
+        }
+    }
+    found
+}
@@ -1,29 +1,682 @@
 use serde::Deserialize;
 use toml;
 
+use chrono::format::{Item, StrftimeItems};
+
+use ostruka::notify::{NotifyEscape, NotifyLevel};
+
+use std::collections::BTreeMap;
+use std::env;
+use std::error;
+use std::fmt;
 use std::fs::File;
 use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Keys accepted at the top level of the config file.
+const KNOWN_KEYS: &[&str] = &["aliases", "user", "user_option_2", "password", "password_command",
+                                "server_address", "autojoin", "timestamp_format", "timestamps",
+                                "highlights", "bell", "notify_escape", "notify_command",
+                                "notify_levels",
+                                "default_profile", "profiles"];
+
+/// Keys accepted inside a `[profiles.<name>]` table.
+const PROFILE_KEYS: &[&str] = &["aliases", "password", "password_command", "server_address",
+                                  "autojoin"];
 
 #[derive(Deserialize)]
 pub struct Config {
-    pub user: String,
-    pub user_option_2: String,
+    /// Aliases to log in with, tried in order until one succeeds.
+    #[serde(default)]
+    pub aliases: Vec<String>,
 
-    pub password: String,
+    // Old form of `aliases`, only used if `aliases` is not given
+    #[serde(default)]
+    user: Option<String>,
+    #[serde(default)]
+    user_option_2: Option<String>,
 
+    /// SHA-1 hex digest of the password. If not given, the password is asked
+    /// when ostruka starts.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Shell command that prints the password, or its digest, to stdout. Only
+    /// used if `password` is not given.
+    #[serde(default)]
+    pub password_command: Option<String>,
+
+    #[serde(default = "default_server_address")]
     pub server_address: String,
+
+    /// Chats joined right after logging in.
+    #[serde(default)]
+    pub autojoin: Vec<String>,
+
+    /// strftime-like format of the time shown next to each line.
+    #[serde(default = "default_timestamp_format")]
+    pub timestamp_format: String,
+    /// Whether timestamps are shown when ostruka starts.
+    #[serde(default = "default_timestamps")]
+    pub timestamps: bool,
+
+    /// Words that mark a received message as a mention, besides the user's alias.
+    #[serde(default)]
+    pub highlights: Vec<String>,
+
+    /// Ring the terminal bell on mentions and direct messages.
+    #[serde(default)]
+    pub bell: bool,
+    /// Escape sequence used to request a desktop notification, `osc9` or `osc777`.
+    #[serde(default)]
+    pub notify_escape: Option<NotifyEscape>,
+    /// Shell command run on mentions and direct messages.
+    #[serde(default)]
+    pub notify_command: Option<String>,
+    /// Notification level of each page: `all`, `mentions` or `none`.
+    #[serde(default)]
+    pub notify_levels: BTreeMap<String, NotifyLevel>,
+
+    /// Profile used when no profile is given in the command line.
+    #[serde(default)]
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+
+    /// Name of the selected profile, if any.
+    #[serde(skip)]
+    pub profile: Option<String>,
+}
+
+/// A named set of connection settings, defined in a `[profiles.<name>]` table.
+/// The values given in the selected profile override the top level ones.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Profile {
+    aliases: Option<Vec<String>>,
+    password: Option<String>,
+    password_command: Option<String>,
+    server_address: Option<String>,
+    autojoin: Option<Vec<String>>,
+}
+
+/// Values given in the command line. They override the ones of the config file and
+/// of the selected profile.
+#[derive(Default)]
+pub struct Overrides {
+    pub server_address: Option<String>,
+    pub alias: Option<String>,
+}
+
+fn default_server_address() -> String {
+    "127.0.0.1:9999".to_string()
+}
+
+fn default_timestamp_format() -> String {
+    "%H:%M".to_string()
+}
+
+fn default_timestamps() -> bool {
+    true
+}
+
+/// Errors that can happen while loading the config file.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// No config file exists in any of the searched locations.
+    NotFound(Vec<PathBuf>),
+    /// The config file exists but cannot be read.
+    Io(PathBuf, io::Error),
+    /// The file is not valid TOML or a value has the wrong type. Contains the
+    /// 1-based line and column of the error, if known.
+    Parse {
+        path: PathBuf,
+        position: Option<(usize, usize)>,
+        message: String,
+    },
+    /// The file contains a key ostruka does not know about. Also contains the
+    /// keys that are accepted in the table where the unknown key was found.
+    UnknownKey {
+        path: PathBuf,
+        key: String,
+        line: Option<usize>,
+        expected: &'static [&'static str],
+    },
+    /// The selected profile is not defined in the config file.
+    UnknownProfile {
+        path: PathBuf,
+        name: String,
+        available: Vec<String>,
+    },
+    /// The config file does not give any alias to log in with.
+    NoAliases(PathBuf),
+    /// The value of the given key is not valid.
+    BadValue {
+        path: PathBuf,
+        key: &'static str,
+        reason: String,
+    },
+    /// The server address is not a valid `host:port` pair. The path is `None` if
+    /// the address was not read from a file.
+    BadAddress {
+        path: Option<PathBuf>,
+        address: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::NotFound(searched) => {
+                write!(f, "no config file found, searched in:")?;
+                for path in searched {
+                    write!(f, "\n    {}", path.display())?;
+                }
+                Ok(())
+            },
+            ConfigError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            ConfigError::Parse { path, position: Some((line, col)), message } =>
+                write!(f, "{}:{}:{}: {}", path.display(), line, col, message),
+            ConfigError::Parse { path, position: None, message } =>
+                write!(f, "{}: {}", path.display(), message),
+            ConfigError::UnknownKey { path, key, line, expected } => {
+                write!(f, "{}", path.display())?;
+                if let Some(line) = line {
+                    write!(f, ":{}", line)?;
+                }
+                write!(f, ": unknown key `{}`, expected one of: {}", key, expected.join(", "))
+            },
+            ConfigError::UnknownProfile { path, name, available } if available.is_empty() =>
+                write!(f, "{}: unknown profile `{}`, no profiles are defined", path.display(), name),
+            ConfigError::UnknownProfile { path, name, available } =>
+                write!(f, "{}: unknown profile `{}`, available profiles: {}",
+                       path.display(), name, available.join(", ")),
+            ConfigError::NoAliases(path) =>
+                write!(f, "{}: no alias to log in with, add an `aliases = [...]` list", path.display()),
+            ConfigError::BadValue { path, key, reason } =>
+                write!(f, "{}: invalid {}: {}", path.display(), key, reason),
+            ConfigError::BadAddress { path, address, reason } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(f, "invalid server_address `{}`: {}", address, reason)
+            },
+        }
+    }
 }
 
+impl error::Error for ConfigError {}
+
 impl Config {
-    
-    pub fn new(config_path: &str) -> Result<Config, io::Error> {
+
+    /// Reads the config file in the given path. If a profile name is given, or the
+    /// file has a `default_profile`, the values of that profile are used. The given
+    /// overrides replace the values of the file before they are checked.
+    pub fn new(config_path: &Path,
+               profile: Option<&str>,
+               overrides: Overrides) -> Result<Config, ConfigError> {
         // Read configuration file
-        let mut file = File::open(config_path)?;
         let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        
+        File::open(config_path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|err| ConfigError::Io(config_path.to_path_buf(), err))?;
+
+        Config::parse(config_path, &contents, profile, overrides)
+    }
+
+    /// Parses the contents of a config file. The path is only used in the errors.
+    fn parse(config_path: &Path,
+             contents: &str,
+             profile: Option<&str>,
+             overrides: Overrides) -> Result<Config, ConfigError> {
+        // Check the syntax of the file and look for unknown keys
+        let value: toml::Value = toml::from_str(contents)
+            .map_err(|err| parse_error(config_path, err))?;
+
+        if let Some(table) = value.as_table() {
+            check_keys(config_path, contents, None, table, KNOWN_KEYS)?;
+
+            if let Some(profiles) = table.get("profiles").and_then(|p| p.as_table()) {
+                for (name, profile) in profiles {
+                    if let Some(profile) = profile.as_table() {
+                        check_keys(config_path, contents, Some(name), profile, PROFILE_KEYS)?;
+                    }
+                }
+            }
+        }
+
         // Deserialize the config string
-        Ok(toml::from_str(&contents)
-           .expect("Unable to deserialize the config file"))
+        let mut config: Config = toml::from_str(contents)
+            .map_err(|err| parse_error(config_path, err))?;
+
+        // Override the top level values with the ones of the selected profile
+        let selected = profile.map(|p| p.to_string()).or_else(|| config.default_profile.clone());
+        if let Some(name) = selected {
+            let profile = match config.profiles.remove(&name) {
+                Some(profile) => profile,
+                None => return Err(ConfigError::UnknownProfile {
+                    path: config_path.to_path_buf(),
+                    name,
+                    available: config.profiles.keys().cloned().collect(),
+                }),
+            };
+            config.apply_profile(profile);
+            config.profile = Some(name);
+        }
+
+        // Support the old `user` and `user_option_2` fields
+        if config.aliases.is_empty() {
+            config.aliases = config.user.take().into_iter()
+                .chain(config.user_option_2.take())
+                .collect();
+        }
+
+        // Explicit command line options override the values of the file
+        let address_path = match overrides.server_address {
+            Some(address) => {
+                config.server_address = address;
+                None
+            },
+            None => Some(config_path.to_path_buf()),
+        };
+        if let Some(alias) = overrides.alias {
+            config.aliases = vec![alias];
+        }
+
+        if config.aliases.is_empty() {
+            return Err(ConfigError::NoAliases(config_path.to_path_buf()));
+        }
+
+        if StrftimeItems::new(&config.timestamp_format).any(|item| item == Item::Error) {
+            return Err(ConfigError::BadValue {
+                path: config_path.to_path_buf(),
+                key: "timestamp_format",
+                reason: format!("`{}` is not a valid time format", config.timestamp_format),
+            });
+        }
+
+        if let Err(reason) = parse_address(&config.server_address) {
+            return Err(ConfigError::BadAddress {
+                path: address_path,
+                address: config.server_address,
+                reason,
+            });
+        }
+
+        Ok(config)
+    }
+
+    /// Loads the config from the given path or, if no path is given, from the first
+    /// config file found by `Config::search_paths`, with the given overrides applied.
+    /// Returns the config and the path of the file it was read from.
+    pub fn load(config_path: Option<&str>,
+                profile: Option<&str>,
+                overrides: Overrides) -> Result<(Config, PathBuf), ConfigError> {
+        let path = match config_path {
+            Some(p) => PathBuf::from(p),
+            None => {
+                let searched = Config::search_paths();
+                match searched.iter().find(|p| p.is_file()) {
+                    Some(p) => p.clone(),
+                    None => return Err(ConfigError::NotFound(searched)),
+                }
+            },
+        };
+
+        let config = Config::new(&path, profile, overrides)?;
+        Ok((config, path))
+    }
+
+    /// Returns the locations where a config file is searched for, by order of priority:
+    /// `$OSTRUKA_CONFIG`, `$XDG_CONFIG_HOME/ostruka/config.toml`,
+    /// `~/.config/ostruka/config.toml` and `./config.toml`.
+    pub fn search_paths() -> Vec<PathBuf> {
+        let mut paths = vec![];
+
+        if let Some(path) = non_empty_var("OSTRUKA_CONFIG") {
+            paths.push(PathBuf::from(path));
+        }
+
+        if let Some(xdg) = non_empty_var("XDG_CONFIG_HOME") {
+            paths.push(PathBuf::from(xdg).join("ostruka").join("config.toml"));
+        }
+
+        if let Some(home) = non_empty_var("HOME") {
+            paths.push(PathBuf::from(home).join(".config").join("ostruka").join("config.toml"));
+        }
+
+        paths.push(PathBuf::from("config.toml"));
+        paths
+    }
+
+    /// Replaces the values of the config with the ones given in the profile.
+    fn apply_profile(&mut self, profile: Profile) {
+        if let Some(aliases) = profile.aliases {
+            self.aliases = aliases;
+            self.user = None;
+            self.user_option_2 = None;
+        }
+
+        // A password source given in the profile replaces both top level sources
+        if profile.password.is_some() || profile.password_command.is_some() {
+            self.password = profile.password;
+            self.password_command = profile.password_command;
+        }
+
+        if let Some(address) = profile.server_address {
+            self.server_address = address;
+        }
+
+        if let Some(autojoin) = profile.autojoin {
+            self.autojoin = autojoin;
+        }
+    }
+}
+
+/// Checks that the address has the `host:port` form, returning a description of the
+/// problem if invalid. The host is not resolved here.
+fn parse_address(address: &str) -> Result<(), String> {
+    let mut parts = address.rsplitn(2, ':');
+    let port = parts.next().unwrap_or("");
+    let host = parts.next().unwrap_or("");
+
+    if host.is_empty() {
+        return Err("expected an address of the form host:port, e.g. localhost:9999".to_string());
+    }
+
+    match port.parse::<u16>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("`{}` is not a valid port number", port)),
+    }
+}
+
+/// Converts a toml error into a `ConfigError::Parse`, with 1-based line and column.
+fn parse_error(path: &Path, err: toml::de::Error) -> ConfigError {
+    let position = err.line_col().map(|(line, col)| (line + 1, col + 1));
+
+    // The toml error already appends the position to its message, remove it as the
+    // position is displayed by `ConfigError` itself.
+    let mut message = err.to_string();
+    if position.is_some() {
+        if let Some(i) = message.rfind(" at line ") {
+            message.truncate(i);
+        }
+    }
+
+    ConfigError::Parse { path: path.to_path_buf(), position, message }
+}
+
+/// Returns an `UnknownKey` error if the table contains a key not in `expected`. The
+/// `profile` is the name of the profile the table belongs to, `None` for the top level.
+fn check_keys(path: &Path,
+              contents: &str,
+              profile: Option<&str>,
+              table: &toml::value::Table,
+              expected: &'static [&'static str]) -> Result<(), ConfigError> {
+
+    match table.keys().find(|k| !expected.contains(&k.as_str())) {
+        Some(key) => Err(ConfigError::UnknownKey {
+            path: path.to_path_buf(),
+            key: match profile {
+                Some(name) => format!("profiles.{}.{}", name, key),
+                None => key.clone(),
+            },
+            line: find_key_line(contents, profile, key),
+            expected,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Returns the 1-based line number where the given key is defined, if found. If a
+/// profile name is given, the key is searched after the profile's table header.
+fn find_key_line(contents: &str, profile: Option<&str>, key: &str) -> Option<usize> {
+    let start = match profile {
+        Some(name) => {
+            let header = format!("[profiles.{}]", name);
+            contents.lines().position(|line| line.trim() == header)? + 1
+        },
+        None => 0,
+    };
+
+    contents.lines()
+        .enumerate()
+        .skip(start)
+        .find(|(_, line)| {
+            let line = line.trim_start();
+            line.starts_with(key) && line[key.len()..].trim_start().starts_with('=')
+        })
+        .map(|(i, _)| i + 1)
+}
+
+/// Returns the value of an environment variable, ignoring variables that are set but empty.
+fn non_empty_var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Result<Config, ConfigError> {
+        Config::parse(Path::new("test.toml"), contents, None, Overrides::default())
+    }
+
+    fn parse_profile(contents: &str, profile: &str) -> Result<Config, ConfigError> {
+        Config::parse(Path::new("test.toml"), contents, Some(profile), Overrides::default())
+    }
+
+    fn parse_overrides(contents: &str, server: Option<&str>, alias: Option<&str>) -> Result<Config, ConfigError> {
+        let overrides = Overrides {
+            server_address: server.map(|s| s.to_string()),
+            alias: alias.map(|a| a.to_string()),
+        };
+        Config::parse(Path::new("test.toml"), contents, None, overrides)
+    }
+
+    const PROFILES: &str = r##"
+aliases = ["anon"]
+password_command = "pass show ostrich"
+server_address = "localhost:9999"
+autojoin = ["#team"]
+default_profile = "home"
+
+[profiles.home]
+server_address = "home.example.com:9999"
+
+[profiles.work]
+aliases = ["worker", "worker_"]
+password = "da39a3ee5e6b4b0d3255bfef95601890afd80709"
+server_address = "work.example.com:7777"
+"##;
+
+    #[test]
+    fn defaults() {
+        let config = parse(r#"aliases = ["anon"]"#).unwrap();
+        assert_eq!(config.aliases, vec!["anon"]);
+        assert_eq!(config.server_address, "127.0.0.1:9999");
+        assert_eq!(config.timestamp_format, "%H:%M");
+        assert!(config.timestamps);
+        assert_eq!(config.profile, None);
+    }
+
+    #[test]
+    fn legacy_user_keys_become_aliases() {
+        let config = parse("user = \"anon\"\nuser_option_2 = \"anon_\"").unwrap();
+        assert_eq!(config.aliases, vec!["anon", "anon_"]);
+
+        let config = parse("user = \"anon\"").unwrap();
+        assert_eq!(config.aliases, vec!["anon"]);
+
+        // `aliases` takes precedence over the old keys
+        let config = parse("aliases = [\"new\"]\nuser = \"old\"").unwrap();
+        assert_eq!(config.aliases, vec!["new"]);
+    }
+
+    #[test]
+    fn missing_aliases() {
+        let err = parse("server_address = \"localhost:9999\"").err().unwrap();
+        assert!(matches!(err, ConfigError::NoAliases(_)));
+        assert_eq!(err.to_string(), "test.toml: no alias to log in with, add an `aliases = [...]` list");
+    }
+
+    #[test]
+    fn default_profile_overrides_given_values() {
+        let config = parse(PROFILES).unwrap();
+        assert_eq!(config.profile.as_deref(), Some("home"));
+        assert_eq!(config.server_address, "home.example.com:9999");
+        // Values not given in the profile are kept
+        assert_eq!(config.aliases, vec!["anon"]);
+        assert_eq!(config.password_command.as_deref(), Some("pass show ostrich"));
+        assert_eq!(config.autojoin, vec!["#team"]);
+    }
+
+    #[test]
+    fn selected_profile_replaces_default_profile() {
+        let config = parse_profile(PROFILES, "work").unwrap();
+        assert_eq!(config.profile.as_deref(), Some("work"));
+        assert_eq!(config.aliases, vec!["worker", "worker_"]);
+        assert_eq!(config.server_address, "work.example.com:7777");
+        assert_eq!(config.autojoin, vec!["#team"]);
+
+        // The password of the profile replaces the top level password command
+        assert_eq!(config.password.as_deref(), Some("da39a3ee5e6b4b0d3255bfef95601890afd80709"));
+        assert_eq!(config.password_command, None);
+    }
+
+    #[test]
+    fn profile_aliases_replace_legacy_user_keys() {
+        let contents = "user = \"old\"\n\n[profiles.new]\naliases = [\"new\"]\n";
+        let config = parse_profile(contents, "new").unwrap();
+        assert_eq!(config.aliases, vec!["new"]);
+    }
+
+    #[test]
+    fn unknown_profile() {
+        let err = parse_profile(PROFILES, "school").err().unwrap();
+        match &err {
+            ConfigError::UnknownProfile { name, available, .. } => {
+                assert_eq!(name, "school");
+                assert_eq!(available, &vec!["home".to_string(), "work".to_string()]);
+            },
+            _ => panic!("unexpected error: {}", err),
+        }
+        assert_eq!(err.to_string(), "test.toml: unknown profile `school`, available profiles: home, work");
+
+        let err = parse_profile("aliases = [\"anon\"]", "school").err().unwrap();
+        assert_eq!(err.to_string(), "test.toml: unknown profile `school`, no profiles are defined");
+    }
+
+    #[test]
+    fn unknown_key_reports_its_line() {
+        let err = parse("aliases = [\"anon\"]\n\ncolour = \"red\"\n").err().unwrap();
+        match &err {
+            ConfigError::UnknownKey { key, line, .. } => {
+                assert_eq!(key, "colour");
+                assert_eq!(*line, Some(3));
+            },
+            _ => panic!("unexpected error: {}", err),
+        }
+        assert!(err.to_string().starts_with("test.toml:3: unknown key `colour`, expected one of: aliases, user,"));
+    }
+
+    #[test]
+    fn unknown_key_line_ignores_longer_keys() {
+        // `alias` is a prefix of `aliases`, which is defined before it
+        let err = parse("aliases = [\"anon\"]\nalias = \"anon\"\n").err().unwrap();
+        assert!(err.to_string().starts_with("test.toml:2: unknown key `alias`"));
+    }
+
+    #[test]
+    fn unknown_profile_key_reports_its_line() {
+        // `timestamps` is valid at the top level but not in a profile
+        let contents = "aliases = [\"anon\"]\ntimestamps = false\n\n[profiles.work]\ntimestamps = true\n";
+        let err = parse(contents).err().unwrap();
+        match &err {
+            ConfigError::UnknownKey { key, line, expected, .. } => {
+                assert_eq!(key, "profiles.work.timestamps");
+                assert_eq!(*line, Some(5));
+                assert_eq!(*expected, PROFILE_KEYS);
+            },
+            _ => panic!("unexpected error: {}", err),
+        }
+        assert_eq!(err.to_string(),
+                   "test.toml:5: unknown key `profiles.work.timestamps`, expected one of: \
+                    aliases, password, password_command, server_address, autojoin");
+    }
+
+    #[test]
+    fn syntax_errors_have_a_position() {
+        let err = parse("aliases = [\"anon\"]\ntimestamps = \n").err().unwrap();
+        match &err {
+            ConfigError::Parse { position, message, .. } => {
+                let (line, col) = position.unwrap();
+                assert_eq!(line, 2);
+                // The position is not repeated in the message
+                assert!(!message.contains(" at line "), "{}", message);
+                assert_eq!(err.to_string(), format!("test.toml:{}:{}: {}", line, col, message));
+            },
+            _ => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn wrong_types_are_parse_errors() {
+        let err = parse("aliases = [\"anon\"]\ntimestamps = \"yes\"\n").err().unwrap();
+        match &err {
+            ConfigError::Parse { message, .. } => assert!(!message.contains(" at line "), "{}", message),
+            _ => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn bad_timestamp_format() {
+        let err = parse("aliases = [\"anon\"]\ntimestamp_format = \"%H:%\"\n").err().unwrap();
+        assert!(matches!(err, ConfigError::BadValue { key: "timestamp_format", .. }));
+        assert_eq!(err.to_string(), "test.toml: invalid timestamp_format: `%H:%` is not a valid time format");
+    }
+
+    #[test]
+    fn address_must_be_host_and_port() {
+        assert!(parse_address("localhost:9999").is_ok());
+        assert!(parse_address("127.0.0.1:1").is_ok());
+        assert!(parse_address("[::1]:9999").is_ok());
+
+        let host_port = "expected an address of the form host:port, e.g. localhost:9999";
+        assert_eq!(parse_address("localhost"), Err(host_port.to_string()));
+        assert_eq!(parse_address(":9999"), Err(host_port.to_string()));
+        assert_eq!(parse_address(""), Err(host_port.to_string()));
+        assert_eq!(parse_address("localhost:"), Err("`` is not a valid port number".to_string()));
+        assert_eq!(parse_address("localhost:99999"), Err("`99999` is not a valid port number".to_string()));
+        assert_eq!(parse_address("localhost:port"), Err("`port` is not a valid port number".to_string()));
+    }
+
+    #[test]
+    fn bad_address() {
+        let err = parse("aliases = [\"anon\"]\nserver_address = \"localhost\"\n").err().unwrap();
+        assert_eq!(err.to_string(), "test.toml: invalid server_address `localhost`: \
+                                     expected an address of the form host:port, e.g. localhost:9999");
+
+        // Addresses given in the command line have no path
+        let err = parse_overrides("aliases = [\"anon\"]", Some("localhost:0x10"), None).err().unwrap();
+        assert_eq!(err.to_string(), "invalid server_address `localhost:0x10`: `0x10` is not a valid port number");
+    }
+
+    #[test]
+    fn command_line_overrides_the_file() {
+        // The alias and the address of the command line are checked instead of the file ones
+        let config = parse_overrides("server_address = \"localhost\"\n",
+                                     Some("example.com:9999"), Some("alice")).unwrap();
+        assert_eq!(config.aliases, vec!["alice"]);
+        assert_eq!(config.server_address, "example.com:9999");
+
+        // They also override the values of the selected profile
+        let overrides = Overrides {
+            server_address: Some("example.com:1".to_string()),
+            alias: Some("alice".to_string()),
+        };
+        let config = Config::parse(Path::new("test.toml"), PROFILES, Some("work"), overrides).unwrap();
+        assert_eq!(config.aliases, vec!["alice"]);
+        assert_eq!(config.server_address, "example.com:1");
     }
 }
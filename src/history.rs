@@ -0,0 +1,180 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Maximum number of entries kept in the history.
+const MAX_ENTRIES: usize = 1000;
+
+/// History of the lines sent by the user, messages and commands. The history is
+/// persisted to a file, one entry per line, so it is kept between sessions.
+pub struct History {
+    entries: Vec<String>, // From oldest to newest
+    position: Option<usize>, // Entry being recalled, None if not browsing the history
+    draft: String, // Text of the buffer before browsing the history
+    path: Option<PathBuf>,
+}
+
+impl History {
+
+    /// Loads the history from the given file. If no path is given or the file does
+    /// not exist, the history starts empty.
+    pub fn load(path: Option<PathBuf>) -> History {
+        let mut entries: Vec<String> = match path.as_ref().map(File::open) {
+            Some(Ok(file)) => BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter(|line| !line.is_empty())
+                .collect(),
+            _ => vec![],
+        };
+
+        // Only the newest entries are kept, rewrite the file if it grew too much
+        if entries.len() > MAX_ENTRIES {
+            entries.drain(..entries.len() - MAX_ENTRIES);
+            if let Some(path) = &path {
+                let _ = open(path, OpenOptions::new().write(true).truncate(true))
+                    .and_then(|mut file| file.write_all((entries.join("\n") + "\n").as_bytes()));
+            }
+        }
+
+        History { entries, position: None, draft: String::new(), path }
+    }
+
+    /// Returns the default location of the history file,
+    /// `$XDG_DATA_HOME/ostruka/history` or `~/.local/share/ostruka/history`.
+    pub fn default_path() -> Option<PathBuf> {
+        let data_dir = match env::var("XDG_DATA_HOME") {
+            Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var("HOME").ok()?).join(".local").join("share"),
+        };
+        Some(data_dir.join("ostruka").join("history"))
+    }
+
+    /// Adds a new entry to the history and appends it to the history file. Empty
+    /// entries and entries equal to the last one are ignored.
+    pub fn push(&mut self, entry: &str) -> Result<(), io::Error> {
+        self.reset();
+
+        if entry.trim().is_empty() || self.entries.last().map(|e| e.as_str()) == Some(entry) {
+            return Ok(());
+        }
+
+        self.entries.push(entry.to_string());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut file = open(path, OpenOptions::new().append(true))?;
+            writeln!(file, "{}", entry)?;
+        }
+        Ok(())
+    }
+
+    /// Stops browsing the history, the next recall starts from the newest entry.
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+
+    /// Returns the entry before the one being recalled. The given text is the current
+    /// content of the buffer, saved to be restored when going past the newest entry.
+    pub fn older(&mut self, current: &str) -> Option<&str> {
+        let index = match self.position {
+            Some(0) => return None,
+            Some(i) => i - 1,
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            },
+        };
+        self.position = Some(index);
+        Some(&self.entries[index])
+    }
+
+    /// Returns the entry after the one being recalled. After the newest entry, the
+    /// text the buffer had before browsing the history is returned.
+    pub fn newer(&mut self) -> Option<&str> {
+        match self.position {
+            None => None,
+            Some(i) if i + 1 < self.entries.len() => {
+                self.position = Some(i + 1);
+                Some(&self.entries[i + 1])
+            },
+            Some(_) => {
+                self.position = None;
+                Some(&self.draft)
+            },
+        }
+    }
+
+    /// Returns the index of the newest entry older than `before` that contains the
+    /// query. If `before` is `None`, the search starts from the newest entry.
+    fn find(&self, query: &str, before: Option<usize>) -> Option<usize> {
+        let end = before.unwrap_or(self.entries.len()).min(self.entries.len());
+        self.entries[..end].iter().rposition(|entry| entry.contains(query))
+    }
+}
+
+/// State of a reverse incremental search in the history.
+pub struct Search {
+    pub query: String,
+    found: Option<usize>, // Index of the matched entry
+    original: String, // Content of the buffer when the search started
+}
+
+impl Search {
+
+    /// Starts a new search. The given text is the content of the buffer, restored if
+    /// the search is cancelled.
+    pub fn new(original: &str) -> Search {
+        Search { query: String::new(), found: None, original: original.to_string() }
+    }
+
+    /// Adds a character to the query, keeping the current match if it still matches.
+    pub fn push(&mut self, c: char, history: &History) {
+        self.query.push(c);
+        self.found = history.find(&self.query, self.found.map(|i| i + 1));
+    }
+
+    /// Removes the last character of the query and searches again from the newest entry.
+    pub fn pop(&mut self, history: &History) {
+        self.query.pop();
+        self.found = if self.query.is_empty() {
+            None
+        } else {
+            history.find(&self.query, None)
+        };
+    }
+
+    /// Looks for an older entry that contains the query.
+    pub fn next(&mut self, history: &History) {
+        if let Some(i) = history.find(&self.query, self.found) {
+            self.found = Some(i);
+        }
+    }
+
+    /// Returns the matched entry, if any.
+    pub fn matched<'a>(&self, history: &'a History) -> Option<&'a str> {
+        self.found.map(|i| history.entries[i].as_str())
+    }
+
+    /// Returns the text the buffer had when the search started.
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+}
+
+/// Opens the history file with the given options, creating it if it does not exist.
+/// The file is only readable by the user, as it contains private messages. Files
+/// created with other permissions are restricted too.
+fn open(path: &Path, options: &mut OpenOptions) -> Result<File, io::Error> {
+    let file = options.create(true).mode(0o600).open(path)?;
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
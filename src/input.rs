@@ -0,0 +1,162 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The user's input line. Holds the typed text and the position of the cursor, which
+/// moves by grapheme clusters so that accented letters or emojis are a single step.
+#[derive(Default)]
+pub struct InputBuffer {
+    text: String,
+    cursor: usize, // Byte index of the cursor, always in a grapheme boundary
+}
+
+impl InputBuffer {
+
+    /// Creates an empty `InputBuffer`.
+    pub fn new() -> InputBuffer {
+        InputBuffer::default()
+    }
+
+    /// Returns the text of the buffer.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Returns the text before the cursor, the grapheme under the cursor (if the cursor
+    /// is not at the end) and the text after it.
+    pub fn split_at_cursor(&self) -> (&str, Option<&str>, &str) {
+        let before = &self.text[..self.cursor];
+        let rest = &self.text[self.cursor..];
+
+        match rest.graphemes(true).next() {
+            Some(current) => (before, Some(current), &rest[current.len()..]),
+            None => (before, None, ""),
+        }
+    }
+
+    /// Replaces the text of the buffer, placing the cursor at the end.
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    /// Removes all the text.
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    /// Inserts a character at the cursor position.
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+
+        // If the character joined the grapheme after the cursor (e.g. a letter typed
+        // before a combining accent), move the cursor to the end of that grapheme
+        let cursor = self.cursor;
+        self.cursor = self.text
+            .grapheme_indices(true)
+            .map(|(i, _)| i)
+            .find(|&i| i >= cursor)
+            .unwrap_or(self.text.len());
+    }
+
+    /// Deletes the grapheme before the cursor.
+    pub fn backspace(&mut self) {
+        let start = self.prev_boundary();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Deletes the grapheme under the cursor.
+    pub fn delete(&mut self) {
+        if let Some(end) = self.next_boundary() {
+            self.text.replace_range(self.cursor..end, "");
+        }
+    }
+
+    /// Moves the cursor one grapheme to the left.
+    pub fn left(&mut self) {
+        self.cursor = self.prev_boundary();
+    }
+
+    /// Moves the cursor one grapheme to the right.
+    pub fn right(&mut self) {
+        if let Some(end) = self.next_boundary() {
+            self.cursor = end;
+        }
+    }
+
+    /// Moves the cursor to the start of the line.
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// Moves the cursor to the end of the line.
+    pub fn end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// Deletes the text from the cursor to the end of the line.
+    pub fn kill_to_end(&mut self) {
+        self.text.truncate(self.cursor);
+    }
+
+    /// Deletes the text from the start of the line to the cursor.
+    pub fn kill_to_start(&mut self) {
+        self.text.replace_range(..self.cursor, "");
+        self.cursor = 0;
+    }
+
+    /// Deletes the word before the cursor, and the whitespace between it and the cursor.
+    pub fn kill_word(&mut self) {
+        let before = &self.text[..self.cursor];
+        let trimmed = before.trim_end();
+        let start = trimmed.rfind(char::is_whitespace)
+            .map(|i| i + trimmed[i..].chars().next().map_or(0, |c| c.len_utf8()))
+            .unwrap_or(0);
+
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Returns the byte index where the word before the cursor starts.
+    pub fn word_start(&self) -> usize {
+        let before = &self.text[..self.cursor];
+        before.rfind(char::is_whitespace)
+            .map(|i| i + before[i..].chars().next().map_or(0, |c| c.len_utf8()))
+            .unwrap_or(0)
+    }
+
+    /// Returns the byte index where the word under the cursor ends.
+    pub fn word_end(&self) -> usize {
+        self.text[self.cursor..].find(char::is_whitespace)
+            .map_or(self.text.len(), |i| self.cursor + i)
+    }
+
+    /// Replaces the text between the given byte index and the end of the word under
+    /// the cursor, placing the cursor after the new text.
+    pub fn replace_word(&mut self, start: usize, text: &str) {
+        self.text.replace_range(start..self.word_end(), text);
+        self.cursor = start + text.len();
+    }
+
+    /// Returns the byte index of the grapheme boundary before the cursor.
+    fn prev_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    /// Returns the byte index of the grapheme boundary after the cursor, if the cursor
+    /// is not at the end.
+    fn next_boundary(&self) -> Option<usize> {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map(|g| self.cursor + g.len())
+    }
+}
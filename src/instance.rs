@@ -1,21 +1,37 @@
+use std::collections::HashMap;
 use std::io;
 use std::ops::Range;
 
+use crate::line::{self, ChatLine, Delivery, LineKind};
+use crate::notify::NotifyLevel;
+use crate::sanitize::{sanitize, sanitize_line};
+use crate::status::Status;
+
 pub struct Page {
     pub name : String,
-    conversation : Vec<String>,
+    conversation : Vec<ChatLine>,
     pub scroll: usize,
-    pub n_lines: usize,
     online_users: Option<Vec<String>>,
+    pending: Vec<Pending>,
+    pub unread: usize, // Number of messages received while the page was not current
+    pub mentioned: bool, // A mention was received while the page was not current
+}
+
+/// A message typed while disconnected from the server, waiting to be sent.
+#[derive(Clone)]
+pub struct Pending {
+    pub sender: String,
+    pub text: String,
+    line: usize, // Index of the line that shows the message in the conversation
 }
 
 impl Page {
 
     /// Creates a new `Page` object. Fields to complete are the name of the 
     /// `Page`and the current conversation.
-    pub fn new(name: String, conversation: Vec<String>) -> Page {
-        let n_lines = conversation.len();
-        Page { name, conversation, scroll: 0, n_lines, online_users: None}
+    pub fn new(name: String, conversation: Vec<ChatLine>) -> Page {
+        Page { name, conversation, scroll: 0, online_users: None, pending: vec![], unread: 0,
+               mentioned: false }
     }
 }
 
@@ -24,18 +40,32 @@ pub struct Instance {
     pages: Vec<Page>, // NOTE: Upgrade to hashmap
     current : usize, // Idex of the current `Page`.
     pub screen_len: Option<usize>,
+    pub status: Status, // State of the connection with the server
+    pub timestamp_format: String, // Format of the time shown next to each line
+    pub show_timestamps: bool,
+    pub highlights: Vec<String>, // Words that mark a received message as a mention
+    pub notify_levels: HashMap<String, NotifyLevel>, // Notification level of each page
 }
 
 impl Instance {
 
     /// Intitializes an empty `Instance` object.
     pub fn new() -> Instance {
-        Instance { pages : Vec::new(), current : 0, screen_len: None}
+        Instance::from(Vec::new(), 0)
     }
     
     /// Intitializes `Instance` object from a vector of `Pages`.
     pub fn from(pages: Vec<Page>, current: usize) -> Instance {
-        Instance { pages, current, screen_len: None }
+        Instance { 
+            pages, 
+            current, 
+            screen_len: None, 
+            status: Status::default(),
+            timestamp_format: "%H:%M".to_string(),
+            show_timestamps: true,
+            highlights: vec![],
+            notify_levels: HashMap::new(),
+        }
     }
     
     /// Adds a `Page` to the current `Instance`. If the page is already added, 
@@ -68,8 +98,8 @@ impl Instance {
         }
     }
     
-    /// Returns a reference to a vector of Strings containing the messages of the current chat.
-    pub fn get_chat(&self) -> Result<Box<Vec<String>>, io::Error> {
+    /// Returns a copy of the lines of the current chat.
+    pub fn get_chat(&self) -> Result<Box<Vec<ChatLine>>, io::Error> {
         let txt = self.pages[self.current]
             .conversation
             .clone();
@@ -94,6 +124,8 @@ impl Instance {
                                "The given index is not a valid index. Number too large."));
         }
         self.current = index;
+        self.pages[index].unread = 0;
+        self.pages[index].mentioned = false;
         Ok(())
     }
     
@@ -111,10 +143,35 @@ impl Instance {
             } else {
                 self.current += 1;
             }
+            self.pages[self.current].unread = 0;
+            self.pages[self.current].mentioned = false;
         }
     } 
 
+    /// Change the current page selection to the previous tab.
+    /// If the current page is the first, select the last page as previous.
+    pub fn previous_page(&mut self) {
+        if self.pages.len() > 1 {
+            if self.current == 0 {
+                self.current = self.pages.len() - 1;
+            } else {
+                self.current -= 1;
+            }
+            self.pages[self.current].unread = 0;
+            self.pages[self.current].mentioned = false;
+        }
+    }
+
+    /// Adds a system line with the given message to the page of the given index. If
+    /// no index is given, the line is added to the current page. The message is
+    /// sanitized, as it might come from the server.
     pub fn add_line(&mut self, index: Option<usize>, message: &str) -> Result<(), io::Error> {
+        self.push_line(index, ChatLine::system(&sanitize(message)))
+    }
+
+    /// Adds a `ChatLine` to the page of the given index. If no index is given, the line
+    /// is added to the current page.
+    pub fn push_line(&mut self, index: Option<usize>, line: ChatLine) -> Result<(), io::Error> {
         let index = match index {
             Some(i) => i,
             None => self.current,
@@ -126,24 +183,24 @@ impl Instance {
                                "The given index is not a valid index. Number too large."));
         }
         
-        if message.contains('\n') {
-            for line in message.split('\n') {
-                self.pages[index].conversation.push(line.to_string());
-                self.pages[index].n_lines += 1;
-            }
-        } else {
-            self.pages[index].conversation.push(message.to_string());
-            self.pages[index].n_lines += 1;
-        }
+        self.pages[index].conversation.push(line);
 
         Ok(())
     }
     
     /// Adds a Command to the page of sender, if sender does not exist creates a new page.
     /// If target's name starts with '#', recognize the message as a group message.
-    pub fn add_msg(&mut self, sender: &str, target: &str, txt: &str) -> Result<(), io::Error> {
+    /// Control characters and escape sequences are removed from the message, and newlines
+    /// are escaped so it is shown in a single row. Returns the name of the page the
+    /// message was added to and whether it mentions the user.
+    pub fn add_msg(&mut self, sender: &str, target: &str, txt: &str) -> Result<(String, bool), io::Error> {
+        // Received text could contain escape sequences that corrupt the terminal
+        let sender = &sanitize_line(sender);
+        let target = &sanitize_line(target);
+        let txt = &sanitize_line(txt);
+
         // Determine if the message is a group message or not
-        let name = if target.starts_with("#") {
+        let name: &str = if target.starts_with("#") {
             target   
         } else {
             sender
@@ -154,26 +211,36 @@ impl Instance {
             .iter()
             .position(|page_name| *page_name == name);
 
-        let formatted = format!("[{}]: {}", sender, txt);
+        let mut line = ChatLine::incoming(sender, txt);
+        line.mention = line::mentions(txt, &self.highlights);
+        let mention = line.mention;
 
         match index {
-            Some(i) => self.add_line(Some(i), &formatted)?,
+            Some(i) => {
+                self.push_line(Some(i), line)?;
+                if i != self.current {
+                    self.pages[i].unread += 1;
+                    self.pages[i].mentioned |= mention;
+                }
+                Ok((name.to_string(), mention))
+            },
             None => {
                 // Create a new page for the sender
-                let page = Page::new(sender.to_string(), vec![formatted]);
+                let mut page = Page::new(sender.to_string(), vec![line]);
+                page.unread = 1;
+                page.mentioned = mention;
                 self.add(page)?;
+                Ok((sender.to_string(), mention))
             },
         }
-
-        Ok(()) 
     }
 
     /// Adds an error description to the current page
     pub fn add_err(&mut self, error: &str) -> Result<(), io::Error> {
-        self.add_line(None, &format!("[ERR]: {}", error))
+        self.push_line(None, ChatLine::error(error))
     }
 
-    pub fn add_chat(&mut self, index: usize, message: &mut Vec<String>) -> Result<(), io::Error> {
+    pub fn add_chat(&mut self, index: usize, message: &mut Vec<ChatLine>) -> Result<(), io::Error> {
         
         // Check for invalid page indexes
         if index > self.pages.len() - 1 {
@@ -214,11 +281,11 @@ impl Instance {
         self.pages[self.current].scroll += 1;
     }
     
-    /// Returns the correct range of chat lines to be displayed (lines of the current page).
-    /// Also controlls the scroll value range before calculating the range.
-    pub fn display_range(&mut self, screen_len: usize) -> Range<usize> {
+    /// Returns the correct range of chat rows to be displayed, given the number of rows
+    /// the current page takes in the screen. Also controlls the scroll value range
+    /// before calculating the range.
+    pub fn display_range(&mut self, screen_len: usize, chat_len: usize) -> Range<usize> {
         // Get values
-        let chat_len = self.pages[self.current].n_lines;
         let scroll = self.get_scroll();
 
         // control scroll value bounds
@@ -282,7 +349,147 @@ impl Instance {
             }
     }
 
+    /// Removes all the usernames in the online_users field of the `Page` of the given
+    /// name. If the given `Page` name does not exist, nothing is done.
+    pub fn clear_online_users(&mut self, page_name: &str) {
+        if let Some(page) = self.pages.iter_mut().find(|page| page.name == page_name) {
+            if page.online_users.is_some() {
+                page.online_users = Some(vec![]);
+            }
+        }
+    }
+
+    /// Returns the number of unread messages of each `Page`, by order.
+    pub fn get_unread(&self) -> Vec<usize> {
+        self.pages.iter().map(|page| page.unread).collect()
+    }
+
+    /// Returns whether each `Page` has unseen mentions, by order.
+    pub fn get_mentioned(&self) -> Vec<bool> {
+        self.pages.iter().map(|page| page.mentioned).collect()
+    }
+
+    /// Returns the notification level of the page of the given name. If no level was
+    /// set for the page, direct chats notify of all messages and groups only of mentions.
+    pub fn notify_level(&self, page_name: &str) -> NotifyLevel {
+        match self.notify_levels.get(page_name) {
+            Some(level) => *level,
+            None if page_name.starts_with('#') => NotifyLevel::Mentions,
+            None => NotifyLevel::All,
+        }
+    }
+
+    /// Returns true if a message received in the page of the given name should notify
+    /// the user, depending on whether it mentions the user.
+    pub fn should_notify(&self, page_name: &str, mention: bool) -> bool {
+        match self.notify_level(page_name) {
+            NotifyLevel::All => true,
+            NotifyLevel::Mentions => mention,
+            NotifyLevel::None => false,
+        }
+    }
+
     pub fn get_online_users(&self) -> Option<Vec<String>> {
         self.pages[self.current].online_users.clone()
     }
+
+    /// Returns the nicknames known in the current page, the senders of the received
+    /// messages from the most recent and then the online users.
+    pub fn nicknames(&self) -> Vec<String> {
+        let page = &self.pages[self.current];
+        let senders = page.conversation.iter()
+            .rev()
+            .filter(|line| line.kind == LineKind::Incoming)
+            .filter_map(|line| line.sender.clone());
+        let online = page.online_users.iter().flatten().cloned();
+
+        let mut nicks: Vec<String> = vec![];
+        for nick in senders.chain(online) {
+            if !nicks.contains(&nick) {
+                nicks.push(nick);
+            }
+        }
+        nicks
+    }
+
+    /// Adds a message that could not be sent to the queue of the current page. The
+    /// message is shown in the page marked as pending.
+    pub fn queue_msg(&mut self, sender: &str, text: &str) -> Result<(), io::Error> {
+        let page = &mut self.pages[self.current];
+        page.pending.push(Pending {
+            sender: sender.to_string(),
+            text: text.to_string(),
+            line: page.conversation.len(),
+        });
+
+        let mut line = ChatLine::own(sender, text);
+        line.delivery = Delivery::Pending;
+        self.push_line(None, line)
+    }
+
+    /// Returns the messages waiting to be sent in the current page, by order.
+    pub fn get_pending(&self) -> Vec<Pending> {
+        self.pages[self.current].pending.clone()
+    }
+
+    /// Removes the message of the given index from the queue of the current page. If
+    /// no index is given, all the queue is removed.
+    pub fn drop_pending(&mut self, index: Option<usize>) -> Result<(), io::Error> {
+        let page = &mut self.pages[self.current];
+
+        let dropped: Vec<Pending> = match index {
+            Some(i) if i < page.pending.len() => vec![page.pending.remove(i)],
+            Some(_) => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                                 "The given index is not a valid queue index.")),
+            None => page.pending.drain(..).collect(),
+        };
+
+        for msg in dropped {
+            page.conversation[msg.line].delivery = Delivery::Dropped;
+        }
+        Ok(())
+    }
+
+    /// Replaces the text of the message of the given index in the queue of the current page.
+    pub fn edit_pending(&mut self, index: usize, text: &str) -> Result<(), io::Error> {
+        let page = &mut self.pages[self.current];
+
+        let msg = match page.pending.get_mut(index) {
+            Some(msg) => msg,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                              "The given index is not a valid queue index.")),
+        };
+
+        msg.text = text.to_string();
+        let line = msg.line;
+        page.conversation[line].body = text.to_string();
+        Ok(())
+    }
+
+    /// Removes and returns the queued messages of all the pages, together with the
+    /// name of the page they belong to. Messages are returned by page and in order.
+    pub fn take_pending(&mut self) -> Vec<(String, Pending)> {
+        let mut queued = vec![];
+        for page in self.pages.iter_mut() {
+            let name = page.name.clone();
+            page.pending.drain(..).for_each(|msg| queued.push((name.clone(), msg)));
+        }
+        queued
+    }
+
+    /// Puts back a message in the queue of the page of the given name. Used when a
+    /// message taken with `take_pending` cannot be sent. If the page does not exist
+    /// anymore, the message is discarded.
+    pub fn requeue(&mut self, page_name: &str, msg: Pending) {
+        if let Some(page) = self.pages.iter_mut().find(|page| page.name == page_name) {
+            page.pending.push(msg);
+        }
+    }
+
+    /// Marks a message taken with `take_pending` as sent in the page of the given name.
+    pub fn mark_sent(&mut self, page_name: &str, msg: &Pending) {
+        if let Some(page) = self.pages.iter_mut().find(|page| page.name == page_name) {
+            page.conversation[msg.line].delivery = Delivery::Sent;
+        }
+    }
 }
@@ -2,7 +2,7 @@ pub use ostruka_core::{Tx, Rx};
 
 use tokio::sync::Mutex;
 
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use tui::Terminal;
 use tui::backend::TermionBackend;
 use termion::raw::IntoRawMode;
@@ -14,12 +14,26 @@ use std::time::Duration;
 use std::sync::Arc;
 
 pub mod commands;
+pub mod complete;
+pub mod history;
+pub mod input;
 pub mod instance;
+pub mod line;
+pub mod notify;
+pub mod parser;
+pub mod sanitize;
+pub mod status;
 mod ui;
 
+use complete::Completion;
+use history::{History, Search};
+use input::InputBuffer;
 use instance::Instance;
 
-pub async fn handle_user(username: &str, mut tx: Tx, mut instance: Arc<Mutex<Instance>>) -> Result<(), io::Error> {
+/// Handles the user's input and draws the TUI. The `Tx` is shared so it can be
+/// replaced when the client reconnects to the server. The alias of the user is read
+/// from the status, as reconnecting might log in with another alias.
+pub async fn handle_user(tx: Arc<Mutex<Tx>>, mut instance: Arc<Mutex<Instance>>) -> Result<(), io::Error> {
     // Set terminal into raw mode
     let stdout = io::stdout().into_raw_mode()?;
     let stdout = AlternateScreen::from(stdout);
@@ -28,77 +42,234 @@ pub async fn handle_user(username: &str, mut tx: Tx, mut instance: Arc<Mutex<Ins
     terminal.hide_cursor()?;
     terminal.clear()?;
 
-    // Init user's input buffer
-    let mut user_buffer = String::new();
+    // Init user's input buffer and the history of sent lines
+    let mut user_buffer = InputBuffer::new();
+    let mut history = History::load(History::default_path());
+    // Active reverse search in the history, if any
+    let mut search: Option<Search> = None;
+    // Active completion of the word before the cursor, if any
+    let mut completion: Option<Completion> = None;
 
     loop {
+        let username = instance.lock().await.status.alias.clone();
+        let username = username.as_str();
+
         // Draw TUI
+        let search_prompt = search.as_ref()
+            .map(|s| (s.query.as_str(), s.matched(&history).unwrap_or("")));
         if let Err(err) = ui::draw_tui(&mut terminal, 
                                        username,
                                        &user_buffer, 
+                                       search_prompt,
                                        &instance).await {
                 terminal.clear().unwrap();
                 eprintln!("Fatal error, unable to draw TUI: {}", err);
                 process::exit(1);
         }
 
-        if let Some(special_key) = buffer_update(&mut user_buffer).unwrap() {
-            // Process the special key
-            match special_key {
-                
-                // The command is finished
-                KeyCode::Enter => {
-                    // Parse command
-                    let command = commands::parse_command(&user_buffer);
-                    // Run the command
-                    if let Err(err) = commands::run_command(username, 
-                                                            &command, 
-                                                            &mut terminal,
-                                                            &mut instance,
-                                                            &mut tx).await {
-                        
-                        // Show error to the user
-                        instance.lock().await
-                            .add_err(&format!("[✗] ERR: {}", err))
-                            .unwrap();
+        let key = match read_key().unwrap() {
+            Some(key) => key,
+            None => continue,
+        };
+
+        // While searching, keys edit the search query
+        let key = match search.as_mut().map(|s| search_update(s, key, &history, &mut user_buffer)) {
+            None => key,
+            Some(SearchStep::Continue) => continue,
+            Some(SearchStep::Finish) => {
+                search = None;
+                continue;
+            },
+            Some(SearchStep::Pass(key)) => {
+                search = None;
+                key
+            },
+        };
+
+        // Tab completes the word before the cursor, pressing it again (or Shift-Tab)
+        // cycles through the candidates. Any other key ends the completion.
+        match key.code {
+            KeyCode::Tab | KeyCode::BackTab => {
+                if completion.is_none() {
+                    let inst = instance.lock().await;
+                    let pages: Vec<String> = inst.names().into_iter().skip(1).collect();
+                    let nicks: Vec<String> = inst.nicknames()
+                        .into_iter()
+                        .filter(|nick| nick != username)
+                        .collect();
+                    completion = Completion::new(&user_buffer, &pages, &nicks);
+                }
+                if let Some(completion) = completion.as_mut() {
+                    match key.code {
+                        KeyCode::BackTab => completion.prev(&mut user_buffer),
+                        _ => completion.next(&mut user_buffer),
                     }
-                    
-                    // Clear user's buffer
-                    user_buffer.clear();
-                },
-
-                KeyCode::Tab => instance.lock().await.next_page(),
-                KeyCode::Down => instance.lock().await.scroll_down(),
-                KeyCode::Up => instance.lock().await.scroll_up(),
-                _ => (),
-            }
+                }
+                continue;
+            },
+            _ => completion = None,
+        }
+
+        let special_key = match buffer_update(&mut user_buffer, key) {
+            Some(key) => key,
+            None => continue,
+        };
+
+        let ctrl = special_key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = special_key.modifiers.contains(KeyModifiers::ALT);
+
+        // Process the special key
+        match special_key.code {
+            
+            // The command is finished
+            KeyCode::Enter => {
+                // Save the line in the history
+                if let Err(err) = history.push(user_buffer.as_str()) {
+                    instance.lock().await
+                        .add_err(&format!("Unable to save the history: {}", err))
+                        .unwrap();
+                }
+
+                // Parse and run the command, errors are shown to the user
+                let result = match parser::parse_command(user_buffer.as_str()) {
+                    Ok(command) => commands::run_command(username, 
+                                                         &command, 
+                                                         &mut terminal,
+                                                         &mut instance,
+                                                         &mut *tx.lock().await).await,
+                    Err(err) => Err(io::Error::new(io::ErrorKind::InvalidInput, err)),
+                };
+                if let Err(err) = result {
+                    instance.lock().await
+                        .add_err(&err.to_string())
+                        .unwrap();
+                }
+                
+                // Clear user's buffer
+                user_buffer.clear();
+            },
+
+            // History recall, Up and Down only recall when the buffer is not empty
+            KeyCode::Char('p') if ctrl => recall_prev(&mut history, &mut user_buffer),
+            KeyCode::Char('n') if ctrl => recall_next(&mut history, &mut user_buffer),
+            KeyCode::Up if !user_buffer.is_empty() => recall_prev(&mut history, &mut user_buffer),
+            KeyCode::Down if !user_buffer.is_empty() => recall_next(&mut history, &mut user_buffer),
+
+            // Start a reverse search in the history
+            KeyCode::Char('r') if ctrl => search = Some(Search::new(user_buffer.as_str())),
+
+            // Page switching
+            KeyCode::Right if alt => instance.lock().await.next_page(),
+            KeyCode::Left if alt => instance.lock().await.previous_page(),
+
+            KeyCode::Down => instance.lock().await.scroll_down(),
+            KeyCode::Up => instance.lock().await.scroll_up(),
+            _ => (),
         }
     }
 }
 
-pub fn buffer_update(buff: &mut String) -> crossterm::Result<Option<KeyCode>> {
+/// Replaces the content of the buffer with the previous entry of the history.
+fn recall_prev(history: &mut History, buff: &mut InputBuffer) {
+    if let Some(entry) = history.older(buff.as_str()) {
+        buff.set(entry);
+    }
+}
+
+/// Replaces the content of the buffer with the next entry of the history.
+fn recall_next(history: &mut History, buff: &mut InputBuffer) {
+    if let Some(entry) = history.newer() {
+        buff.set(entry);
+    }
+}
+
+/// What to do after applying a key to a reverse search.
+enum SearchStep {
+    Continue,       // Keep searching
+    Finish,         // The search is over
+    Pass(KeyEvent), // The search is over and the key has to be processed as usual
+}
+
+/// Applies a key to an active reverse search. Ctrl-R looks for an older match, Esc and
+/// Ctrl-G cancel the search, Enter accepts the match into the buffer without sending
+/// it, and any other special key accepts the match and is processed as usual.
+fn search_update(search: &mut Search, 
+                 key: KeyEvent, 
+                 history: &History, 
+                 buff: &mut InputBuffer) -> SearchStep {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+    match key.code {
+        KeyCode::Char('r') if ctrl => search.next(history),
+        KeyCode::Char('g') if ctrl => {
+            buff.set(search.original());
+            return SearchStep::Finish;
+        },
+        KeyCode::Esc => {
+            buff.set(search.original());
+            return SearchStep::Finish;
+        },
+        KeyCode::Char(c) if !ctrl => search.push(c, history),
+        KeyCode::Backspace => search.pop(history),
+        _ => {
+            if let Some(entry) = search.matched(history) {
+                buff.set(entry);
+            }
+            return match key.code {
+                KeyCode::Enter => SearchStep::Finish,
+                _ => SearchStep::Pass(key),
+            };
+        },
+    }
+    SearchStep::Continue
+}
+
+/// Waits for a key press for a short time. Returns `None` if no key was pressed.
+pub fn read_key() -> crossterm::Result<Option<KeyEvent>> {
     // `poll()` waits for an `Event` for a given time period
     if event::poll(Duration::from_millis(100))? {
-        match event::read()? {
-            Event::Key(ev) => {
-                match ev.code {
-                    // If letter is given, add it to buffer
-                    KeyCode::Char(c) => buff.push(c),
-
-                    // If backspace, delete last word
-                    KeyCode::Backspace => match buff.pop() {
-                       Some(_c) => (),
-                       None => (),
-                    },
-                    
-                    // Else, return the special key pressed
-                    _ => return Ok(Some(ev.code)),
-                }
-            },
-            _ => (),
+        if let Event::Key(ev) = event::read()? {
+            return Ok(Some(ev));
         }
     } else {
         // Timeout expired and no `Event` is available
     }
     Ok(None)
-}    
+}
+
+/// Applies a key press to the input buffer. Keys that do not edit the buffer are
+/// returned. Editing keys include the arrows, Home, End, Delete and the Emacs-style
+/// Ctrl-A, Ctrl-E, Ctrl-K, Ctrl-U and Ctrl-W. Keys pressed with Alt are always returned.
+pub fn buffer_update(buff: &mut InputBuffer, ev: KeyEvent) -> Option<KeyEvent> {
+    if ev.modifiers.contains(KeyModifiers::ALT) {
+        return Some(ev);
+    } else if ev.modifiers.contains(KeyModifiers::CONTROL) {
+        match ev.code {
+            KeyCode::Char('a') => buff.home(),
+            KeyCode::Char('e') => buff.end(),
+            KeyCode::Char('k') => buff.kill_to_end(),
+            KeyCode::Char('u') => buff.kill_to_start(),
+            KeyCode::Char('w') => buff.kill_word(),
+            _ => return Some(ev),
+        }
+    } else {
+        match ev.code {
+            // If letter is given, add it to buffer
+            KeyCode::Char(c) => buff.insert(c),
+
+            // If backspace, delete the character before the cursor
+            KeyCode::Backspace => buff.backspace(),
+            KeyCode::Delete => buff.delete(),
+
+            // Cursor movement
+            KeyCode::Left => buff.left(),
+            KeyCode::Right => buff.right(),
+            KeyCode::Home => buff.home(),
+            KeyCode::End => buff.end(),
+            
+            // Else, return the special key pressed
+            _ => return Some(ev),
+        }
+    }
+    None
+}
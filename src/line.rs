@@ -0,0 +1,88 @@
+use chrono::{DateTime, Local};
+
+/// The kind of a line in a conversation, determines how the line is displayed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LineKind {
+    Own,      // Message sent by the user
+    Incoming, // Message received from another user
+    System,   // Information from ostruka or the server
+    Error,
+    Presence, // Joins, leaves and reconnections
+}
+
+/// Delivery state of the messages sent by the user.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Delivery {
+    Sent,
+    Pending, // Typed while disconnected, waiting to be sent
+    Dropped, // Removed from the queue by the user, never sent
+}
+
+/// A line of a conversation.
+#[derive(Clone, Debug)]
+pub struct ChatLine {
+    pub time: DateTime<Local>,
+    pub sender: Option<String>,
+    pub kind: LineKind,
+    pub body: String,
+    pub delivery: Delivery,
+    pub mention: bool, // The body mentions the user's alias or a highlight word
+}
+
+impl ChatLine {
+
+    /// Creates a new `ChatLine` stamped with the current local time.
+    pub fn new(kind: LineKind, sender: Option<&str>, body: &str) -> ChatLine {
+        ChatLine {
+            time: Local::now(),
+            sender: sender.map(|s| s.to_string()),
+            kind,
+            body: body.to_string(),
+            delivery: Delivery::Sent,
+            mention: false,
+        }
+    }
+
+    /// Creates a `ChatLine` with a message sent by the user.
+    pub fn own(sender: &str, body: &str) -> ChatLine {
+        ChatLine::new(LineKind::Own, Some(sender), body)
+    }
+
+    /// Creates a `ChatLine` with a message received from another user.
+    pub fn incoming(sender: &str, body: &str) -> ChatLine {
+        ChatLine::new(LineKind::Incoming, Some(sender), body)
+    }
+
+    /// Creates a `ChatLine` with information from ostruka or the server.
+    pub fn system(body: &str) -> ChatLine {
+        ChatLine::new(LineKind::System, None, body)
+    }
+
+    /// Creates a `ChatLine` with an error description.
+    pub fn error(body: &str) -> ChatLine {
+        ChatLine::new(LineKind::Error, None, body)
+    }
+
+    /// Creates a `ChatLine` about a join, leave or reconnection.
+    pub fn presence(body: &str) -> ChatLine {
+        ChatLine::new(LineKind::Presence, None, body)
+    }
+}
+
+/// Returns true if the text contains any of the given words as a whole word, ignoring
+/// case. For example, `"hi bob!"` contains `"Bob"` but `"bobby"` does not.
+pub fn mentions(text: &str, words: &[String]) -> bool {
+    let text = text.to_lowercase();
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+
+    words.iter()
+        .filter(|word| !word.is_empty())
+        .any(|word| {
+            let word = word.to_lowercase();
+            text.match_indices(&word).any(|(i, _)| {
+                let before = text[..i].chars().next_back();
+                let after = text[i + word.len()..].chars().next();
+                !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+            })
+        })
+}
@@ -0,0 +1,188 @@
+use tokio::net;
+use tokio::sync::Mutex;
+use tokio::time::delay_for;
+
+use ostruka_core::Client;
+use ostruka::Tx;
+use ostruka::instance::Instance;
+use ostruka::line::ChatLine;
+use ostruka::status::ConnState;
+
+use sha1::Sha1;
+use termion::input::TermRead;
+
+use std::io::{self, BufRead, Write};
+use std::net::SocketAddr;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Delay before the first reconnection attempt.
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+/// Maximum delay between two reconnection attempts.
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// Resolves the server's `host:port` address into a list of socket addresses.
+/// Both IPv6 and IPv4 addresses are returned, in the order given by the resolver.
+pub async fn resolve(address: &str) -> Result<Vec<SocketAddr>, io::Error> {
+    let addrs: Vec<SocketAddr> = net::lookup_host(address).await?.collect();
+
+    if addrs.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound,
+                                  format!("{} did not resolve to any address", address)));
+    }
+    Ok(addrs)
+}
+
+/// Tries to log in with the given alias in each of the given addresses, in order,
+/// until one of them succeeds. Returns the client, its `Tx` and the address that
+/// accepted the login. If every address fails, the last error is returned.
+pub async fn log_in(alias: &str,
+                    password: &str,
+                    addrs: &[SocketAddr]) -> Result<(Client, Tx, SocketAddr), String> {
+
+    let mut last_err = String::from("no address to connect to");
+
+    for addr in addrs {
+        match Client::log_in(alias, password, *addr).await {
+            Ok((client, tx)) => return Ok((client, tx, *addr)),
+            Err(err) => last_err = format!("{}: {}", addr, err),
+        }
+    }
+
+    Err(last_err)
+}
+
+/// Logs in again after the connection with the server was lost. Tries until it
+/// succeeds, waiting between attempts with a capped exponential backoff with jitter.
+/// In each attempt the given alias is tried first and then the other aliases, in
+/// order, as the server might still hold the old session. Returns the connection and
+/// the alias that logged in. Failed attempts are shown in the home page.
+pub async fn reconnect(alias: &str,
+                       aliases: &[String],
+                       password: &str,
+                       address: &str,
+                       instance: &Arc<Mutex<Instance>>) -> (Client, Tx, SocketAddr, String) {
+    let mut attempt = 0;
+
+    let mut candidates = vec![alias];
+    candidates.extend(aliases.iter().map(|a| a.as_str()).filter(|a| *a != alias));
+
+    loop {
+        let delay = backoff_delay(attempt);
+        instance.lock().await
+            .add_line(Some(0), &format!("Reconnecting in {:.1}s...", delay.as_secs_f32()))
+            .unwrap();
+        delay_for(delay).await;
+
+        instance.lock().await.status.set_state(ConnState::Connecting);
+
+        // The address is resolved in each attempt, as it might have changed
+        match resolve(address).await {
+            Ok(addrs) => {
+                for candidate in &candidates {
+                    match log_in(candidate, password, &addrs).await {
+                        Ok((client, tx, addr)) => return (client, tx, addr, candidate.to_string()),
+                        Err(err) => instance.lock().await
+                            .push_line(Some(0), ChatLine::error(&format!("Unable to reconnect as {}: {}",
+                                                                         candidate, err)))
+                            .unwrap(),
+                    }
+                }
+            },
+            Err(err) => instance.lock().await
+                .push_line(Some(0), ChatLine::error(&format!("Unable to reconnect: {}", err)))
+                .unwrap(),
+        }
+
+        instance.lock().await.status.set_state(ConnState::Reconnecting);
+        attempt += 1;
+    }
+}
+
+/// Returns the delay to wait before the given reconnection attempt. The delay doubles
+/// with each attempt up to `BACKOFF_MAX`, and a random half of it is skipped so that
+/// clients disconnected at the same time do not reconnect at the same time.
+fn backoff_delay(attempt: u32) -> Duration {
+    let delay = BACKOFF_BASE.checked_mul(1 << attempt.min(16))
+        .unwrap_or(BACKOFF_MAX)
+        .min(BACKOFF_MAX);
+
+    // The nanoseconds of the current time are random enough for jitter
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let jitter = delay / 2 * (nanos % 1000) / 1000;
+
+    delay / 2 + jitter
+}
+
+/// Returns the SHA-1 hex digest of the given password, this is the form in which
+/// passwords are sent to the server and stored in the config file.
+pub fn hash_password(password: &str) -> String {
+    Sha1::from(password).digest().to_string()
+}
+
+/// Returns true if the given string looks like a SHA-1 hex digest.
+pub fn is_sha1_digest(s: &str) -> bool {
+    s.len() == 40 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Runs the given shell command and returns the password it prints to stdout. If the
+/// output is already a SHA-1 digest it is returned as is, otherwise it is hashed.
+pub fn run_password_command(command: &str) -> Result<String, io::Error> {
+    // stdin and stderr are inherited, so tools like `pass` can ask for a passphrase
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| io::Error::new(err.kind(),
+                                      format!("Unable to run `{}`: {}", command, err)))?;
+
+    if !output.status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other,
+                                  format!("`{}` failed with {}", command, output.status)));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let password = stdout.trim();
+
+    if password.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                  format!("`{}` did not print any password", command)));
+    }
+
+    if is_sha1_digest(password) {
+        Ok(password.to_lowercase())
+    } else {
+        Ok(hash_password(password))
+    }
+}
+
+/// Asks the user for a password. The input is hidden if stdin is a terminal,
+/// otherwise a line is read from stdin as is.
+pub fn prompt_password(prompt: &str) -> Result<String, io::Error> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    if !termion::is_tty(&stdin) {
+        let mut line = String::new();
+        stdin.lock().read_line(&mut line)?;
+        return Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string());
+    }
+
+    write!(stdout, "{}", prompt)?;
+    stdout.flush()?;
+
+    let password = stdin.lock().read_passwd(&mut stdout)?;
+    // The newline typed by the user is not echoed
+    writeln!(stdout)?;
+
+    match password {
+        Some(p) => Ok(p),
+        None => Err(io::Error::new(io::ErrorKind::Interrupted, "Password prompt aborted")),
+    }
+}
@@ -6,71 +6,142 @@ use tokio::stream::StreamExt;
 use ostrich_core::*;
 use ostruka_core::{Client, Message};
 
-use std::net::SocketAddr;
+use std::env;
 use std::sync::Arc;
 use std::process;
 //use std::time::Duration;
 
-use ostruka::{handle_user, instance};
+use ostruka::{handle_user, commands, instance};
+use ostruka::status::{ConnState, Status};
+use ostruka::line::ChatLine;
+use ostruka::notify::Notifier;
+use ostruka::sanitize::sanitize_line;
 use instance::{Instance, Page};
 
+mod cli;
+use cli::Action;
+
 mod config;
-use config::Config;
+use config::{Config, Overrides};
+
+mod login;
+
+/// Exit status used when ostruka cannot log in to the server.
+const EXIT_LOGIN: i32 = 1;
+/// Exit status used when the command line arguments are not valid.
+const EXIT_USAGE: i32 = 2;
+/// Exit status used when the configuration cannot be loaded or is not valid.
+const EXIT_CONFIG: i32 = 3;
 
 #[tokio::main]
 async fn main() {
-    
-    // Read the config file and deserialize it into Config
-    let config = Config::new("config.toml").unwrap();
-
-    let addr: SocketAddr = config.server_address.parse().unwrap();
 
-    println!("Trying to log in as {} in {}...", 
-             config.user, config.server_address);
+    // Parse command line arguments
+    let args = match cli::parse(env::args()) {
+        Ok(Action::Run(args)) => args,
+        Ok(Action::HashPassword) => {
+            match login::prompt_password("Password: ") {
+                Ok(password) => println!("{}", login::hash_password(&password)),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(EXIT_USAGE);
+                },
+            }
+            process::exit(0);
+        },
+        Ok(Action::Help) => {
+            println!("{}", cli::USAGE);
+            process::exit(0);
+        },
+        Ok(Action::Version) => {
+            println!("ostruka {}", env!("CARGO_PKG_VERSION"));
+            process::exit(0);
+        },
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            process::exit(EXIT_USAGE);
+        },
+    };
     
-    // The name variable is initialized in this point as its no clear wich of the username options
-    // is going to be chosen before trying to log in.
-    let user_alias: String;
-
-    // Create a client and try to log in
-    let (mut client, tx) = match Client::log_in(&config.user, 
-                                                &config.password, 
-                                                addr).await {
-        Ok(result) => {
-            user_alias = config.user; // The first alias is selected
-            result
+    // Find the config file and deserialize it into Config. Explicit command line
+    // options override the values of the config file.
+    let overrides = Overrides { server_address: args.server, alias: args.user };
+    let mut config = match Config::load(args.config.as_deref(), args.profile.as_deref(), overrides) {
+        Ok((config, _path)) => config,
+        Err(err) => {
+            eprintln!("Configuration error: {}", err);
+            process::exit(EXIT_CONFIG);
         },
+    };
+
+    // Resolve the server's address, a hostname might resolve to many addresses
+    let addrs = match login::resolve(&config.server_address).await {
+        Ok(addrs) => addrs,
         Err(err) => {
-            // The first try was unsuccessful, try with the second option
-            eprintln!("Unable to log in as {} in {}. Error: {}", 
-                      config.user, addr, err);
-            println!("Trying to log in as {}", config.user_option_2);
-            
-            // Second try
-            match Client::log_in(&config.user_option_2,
-                                 &config.password,
-                                 addr).await {
-                Ok(result) => {
-                    user_alias = config.user_option_2; // The second alias is selected
-                    result
-                },
-                Err(err) => {
-                    // Failed to log in again, time to exit
-                    eprintln!("Unable to log in as {} in {}. Error: {}", 
-                              config.user_option_2, addr, err);
-                    eprintln!("Cannot connect to server. Exiting...");
-                    process::exit(1);
-                },
+            eprintln!("Unable to resolve {}. Error: {}", config.server_address, err);
+            process::exit(EXIT_LOGIN);
+        },
+    };
+
+    // If the password is not in the config, get it from the password command or
+    // ask for it. In both cases it is hashed locally if needed.
+    let password = match (config.password.take(), &config.password_command) {
+        (Some(password), _) => Ok(password),
+        (None, Some(command)) => login::run_password_command(command),
+        (None, None) => login::prompt_password(&format!("Password for {}: ", config.server_address))
+            .map(|password| login::hash_password(&password)),
+    };
+
+    let password = match password {
+        Ok(password) => password,
+        Err(err) => {
+            eprintln!("Unable to get the password: {}", err);
+            process::exit(EXIT_LOGIN);
+        },
+    };
+
+    // Try to log in with each alias, in order. The reason of each failure is
+    // saved to show a summary if none of the aliases can log in.
+    let mut failures = vec![];
+    let mut logged_in = None;
+
+    for alias in &config.aliases {
+        println!("Trying to log in as {} in {}...", alias, config.server_address);
+
+        match login::log_in(alias, &password, &addrs).await {
+            Ok(result) => {
+                logged_in = Some((alias.clone(), result));
+                break;
+            },
+            Err(err) => failures.push((alias, err)),
+        }
+    }
+
+    let (mut user_alias, (mut client, mut tx, addr)) = match logged_in {
+        Some(result) => result,
+        None => {
+            eprintln!("Unable to log in to {}:", config.server_address);
+            for (alias, err) in failures {
+                eprintln!("    {}: {}", alias, err);
             }
+            eprintln!("Cannot connect to server. Exiting...");
+            process::exit(EXIT_LOGIN);
         },
     };
 
+    println!("Logged in as {} in {} ({})", user_alias, config.server_address, addr);
+    if let Some(profile) = &config.profile {
+        println!("Using profile {}", profile);
+    }
+
     // Init pages
     let home_page = Page::new("ostruka".to_string(), vec![
-        "---------------------------------------".to_string(),
-        "Welcome to ostruka the ostrich client!".to_string(),
-        "--------------------------------------".to_string(),
-        "".to_string(),
+        ChatLine::system("---------------------------------------"),
+        ChatLine::system("Welcome to ostruka the ostrich client!"),
+        ChatLine::system("--------------------------------------"),
+        ChatLine::system("Type :help to list the commands."),
+        ChatLine::system(""),
+        ChatLine::presence(&format!("Connected to {} ({})", config.server_address, addr)),
     ]);
 
     // Init Instance
@@ -82,21 +153,133 @@ async fn main() {
 
     // Set Homepage as current Page
     instance.lock().await.set_current(0).unwrap();
+
+    // Timestamp settings of the config
+    instance.lock().await.timestamp_format = config.timestamp_format.clone();
+    instance.lock().await.show_timestamps = config.timestamps;
+
+    // Messages that contain the alias or a highlight word are mentions
+    let mut highlights = config.highlights.clone();
+    highlights.push(user_alias.clone());
+    instance.lock().await.highlights = highlights;
+
+    // Notification settings of the config
+    instance.lock().await.notify_levels = config.notify_levels.clone().into_iter().collect();
+    let notifier = Notifier {
+        bell: config.bell,
+        escape: config.notify_escape,
+        command: config.notify_command.clone(),
+    };
+
+    // Show the connection in the status line
+    let mut status = Status::new(&config.server_address, &user_alias);
+    status.set_state(ConnState::Connected);
+    instance.lock().await.status = status;
+
+    // Join the chats of the autojoin list. Failed joins are shown in the home page.
+    for name in &config.autojoin {
+        if let Err(err) = commands::join(name, &instance, &mut tx).await {
+            instance.lock().await
+                .add_err(&format!("Unable to join {}: {}", name, err))
+                .unwrap();
+        }
+    }
     
+    // The `Tx` is shared with the user handler, so it can be replaced after reconnecting
+    let tx = Arc::new(Mutex::new(tx));
+    let tx_clone = Arc::clone(&tx);
+
     // Launch the user handler
     tokio::spawn(async move {
-        if let Err(err) = handle_user(&user_alias, tx, instance_clone).await {
+        if let Err(err) = handle_user(tx_clone, instance_clone).await {
             panic!("Fatal error: {}", err);
         }
     });
 
-    while let Some(Ok(mesg)) = client.next().await {
+    loop {
+        // Handle messages until the connection is lost
+        let reason = run_client(client, &instance, &notifier).await;
+        {
+            let mut instance = instance.lock().await;
+            instance.status.set_state(ConnState::Reconnecting);
+            instance.push_line(Some(0), ChatLine::error(&format!("Disconnected from {}: {}", 
+                                                                 config.server_address, reason)))
+                .unwrap();
+        }
+
+        // Log in again and replace the `Tx` used by the user handler
+        let (new_client, mut new_tx, addr, alias) = login::reconnect(&user_alias,
+                                                                     &config.aliases,
+                                                                     &password,
+                                                                     &config.server_address,
+                                                                     &instance).await;
+        client = new_client;
+
+        {
+            let mut instance = instance.lock().await;
+            instance.status.set_state(ConnState::Connected);
+            instance.push_line(Some(0), ChatLine::presence(&format!("Reconnected to {} ({})", 
+                                                                    config.server_address, addr)))
+                .unwrap();
+
+            // The old alias might still be in use, the user handler reads the new
+            // one from the status
+            if alias != user_alias {
+                instance.push_line(Some(0), ChatLine::presence(&format!("Logged in as {} instead of {}",
+                                                                        alias, user_alias)))
+                    .unwrap();
+                for word in instance.highlights.iter_mut().filter(|word| **word == user_alias) {
+                    *word = alias.clone();
+                }
+                instance.status.alias = alias.clone();
+                user_alias = alias;
+            }
+        }
+
+        // The shared `Tx` is locked until the queue is flushed, so messages typed in the
+        // meantime wait and are sent after the queued ones
+        let mut shared_tx = tx.lock().await;
+
+        // Join again the chats that were open before the disconnection
+        if let Err(err) = commands::rejoin(&instance, &mut new_tx).await {
+            instance.lock().await
+                .push_line(Some(0), ChatLine::error(&format!("Unable to rejoin the open chats: {}", err)))
+                .unwrap();
+        }
+
+        // Send the messages typed while disconnected
+        match commands::flush_queue(&instance, &mut new_tx).await {
+            Ok(0) => (),
+            Ok(n) => instance.lock().await
+                .add_line(Some(0), &format!("Sent {} queued messages", n))
+                .unwrap(),
+            Err(err) => instance.lock().await
+                .push_line(Some(0), ChatLine::error(&format!("Unable to send the queued messages: {}", err)))
+                .unwrap(),
+        }
+
+        *shared_tx = new_tx;
+    }
+}
+
+/// Handles the messages of the client until the connection with the server is lost.
+/// Returns the reason of the disconnection.
+async fn run_client(mut client: Client, 
+                    instance: &Arc<Mutex<Instance>>,
+                    notifier: &Notifier) -> String {
+    loop {
+        let mesg = match client.next().await {
+            Some(Ok(mesg)) => mesg,
+            Some(Err(err)) => return err.to_string(),
+            None => return "connection closed by the server".to_string(),
+        };
+
         match mesg {
             // Send a command to the server
             Message::ToSend(cmd) => {
                 if let Err(err) = client.send_cmd(&cmd).await {
                     instance.lock().await
-                        .add_err(&format!("[✗] SERVER: {}", err))
+                        .add_err(&format!("SERVER: {}", err))
                         .unwrap();
                 }
             },
@@ -105,10 +288,23 @@ async fn main() {
             Message::Received(Command::Msg(sender, target, txt)) => {
                 let result = instance.lock().await.add_msg(&sender, &target, &txt);
 
-                if let Err(err) = result {
-                    instance.lock().await
-                        .add_err(&err.to_string())
-                        .unwrap()
+                let (page, mention) = match result {
+                    Ok(added) => added,
+                    Err(err) => {
+                        instance.lock().await
+                            .add_err(&err.to_string())
+                            .unwrap();
+                        continue;
+                    },
+                };
+
+                // Notify the user about mentions and direct messages
+                if instance.lock().await.should_notify(&page, mention) {
+                    if let Err(err) = notifier.notify(&sender, &page, &txt) {
+                        instance.lock().await
+                            .add_line(Some(0), &format!("Unable to notify: {}", err))
+                            .unwrap()
+                    }
                 }
             },
             Message::Received(Command::ListUsr(group, op, users)) => {
@@ -119,7 +315,10 @@ async fn main() {
                 let mut list: Vec<String> = vec![];
                 // NOTE: This if len > 0 condition is needed as the splitted vector might 
                 // contain some empty items sometimes.
-                splitted.for_each(|s| if s.len() > 0 { list.push(s.to_string()) });
+                // Nicknames are sanitized like the senders of messages, as they are shown
+                // in the list of online users
+                splitted.for_each(|s| if s.len() > 0 { list.push(sanitize_line(s)) });
+                let group = sanitize_line(&group);
                 match op {
                     ListUsrOperation::Add => instance.lock().await.add_online_users(&group, list),
                     ListUsrOperation::Remove => instance.lock().await.remove_online_users(&group, list),
@@ -0,0 +1,95 @@
+use serde::Deserialize;
+
+use tokio::process::Command;
+
+use std::io::{self, Write};
+use std::process::Stdio;
+use std::str::FromStr;
+
+use crate::sanitize::sanitize;
+
+/// Messages of a page that trigger a notification.
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyLevel {
+    All,      // Every received message
+    Mentions, // Only messages that mention the user
+    None,     // Muted
+}
+
+impl FromStr for NotifyLevel {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<NotifyLevel, io::Error> {
+        match s {
+            "all" => Ok(NotifyLevel::All),
+            "mentions" => Ok(NotifyLevel::Mentions),
+            "none" => Ok(NotifyLevel::None),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                    format!("Unknown notification level {}, \
+                                            expected all, mentions or none", s))),
+        }
+    }
+}
+
+/// Terminal escape sequence used to request a desktop notification.
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyEscape {
+    Osc9,   // Supported by iTerm2, kitty, Windows Terminal...
+    Osc777, // Supported by urxvt, foot, VTE based terminals...
+}
+
+/// Notifies the user about mentions and direct messages.
+#[derive(Default)]
+pub struct Notifier {
+    pub bell: bool,                    // Ring the terminal bell
+    pub escape: Option<NotifyEscape>,  // Emit a desktop notification escape sequence
+    pub command: Option<String>,       // Shell command to run on each notification
+}
+
+impl Notifier {
+
+    /// Notifies about a message from the given sender in the given page.
+    pub fn notify(&self, sender: &str, page: &str, text: &str) -> Result<(), io::Error> {
+        let mut stdout = io::stdout();
+
+        if self.bell {
+            write!(stdout, "\x07")?;
+        }
+
+        // Control characters would end the escape sequence early
+        let title = printable(&format!("{} in {}", sender, page));
+        let body = printable(text);
+        match self.escape {
+            Some(NotifyEscape::Osc9) => write!(stdout, "\x1b]9;{}: {}\x07", title, body)?,
+            Some(NotifyEscape::Osc777) => write!(stdout, "\x1b]777;notify;{};{}\x07",
+                                                 title, body.replace(';', ","))?,
+            None => (),
+        }
+        stdout.flush()?;
+
+        // The command gets the sender, page and text as positional arguments and as
+        // environment variables. It is not waited for, so it does not block ostruka.
+        if let Some(command) = &self.command {
+            Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .arg("ostruka")
+                .args(&[sender, page, text])
+                .env("OSTRUKA_SENDER", sender)
+                .env("OSTRUKA_PAGE", page)
+                .env("OSTRUKA_TEXT", text)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()?;
+        }
+        Ok(())
+    }
+}
+
+/// Removes the escape sequences and control characters of the given text.
+fn printable(text: &str) -> String {
+    sanitize(text).chars().filter(|c| !c.is_control()).collect()
+}
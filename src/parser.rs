@@ -0,0 +1,425 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::commands::{QueueAction, UserCommand};
+use crate::notify::NotifyLevel;
+
+/// Kind of value taken by an argument.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ArgKind {
+    Word,   // A single token, quoted if it contains whitespace
+    Number, // A non-negative integer
+    Level,  // A notification level
+    Rest,   // The rest of the line, as typed
+}
+
+impl ArgKind {
+    /// Describes the values accepted by the kind.
+    fn expected(self) -> &'static str {
+        match self {
+            ArgKind::Word | ArgKind::Rest => "some text",
+            ArgKind::Number => "a number",
+            ArgKind::Level => "all, mentions or none",
+        }
+    }
+}
+
+/// Argument of a command.
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub optional: bool,
+}
+
+const fn required(name: &'static str, kind: ArgKind) -> ArgSpec {
+    ArgSpec { name, kind, optional: false }
+}
+
+const fn optional(name: &'static str, kind: ArgKind) -> ArgSpec {
+    ArgSpec { name, kind, optional: true }
+}
+
+/// Entry of the command table. The name of a subcommand includes the name of its
+/// command, e.g. `queue drop`. Aliases replace the first word of the name.
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub args: &'static [ArgSpec],
+    pub description: &'static str, // Shown by `:help`
+    build: fn(&Args) -> UserCommand, // Builds the command from the checked arguments
+}
+
+/// Table of the commands typed after a `:`. Changing the page with `:<n>` is not in the
+/// table, a number is never a command name, see `CHANGE_PAGE`.
+pub static COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "join",
+        aliases: &[],
+        args: &[required("page", ArgKind::Word)],
+        description: "Join a group (names starting with #) or a direct chat with a user",
+        build: |args| UserCommand::Join(args.text(0)),
+    },
+    CommandSpec {
+        name: "close",
+        aliases: &["q"],
+        args: &[],
+        description: "Close the current page",
+        build: |_| UserCommand::Close,
+    },
+    CommandSpec {
+        name: "timestamps",
+        aliases: &["ts"],
+        args: &[],
+        description: "Show or hide the time of each line",
+        build: |_| UserCommand::ToggleTimestamps,
+    },
+    CommandSpec {
+        name: "notify",
+        aliases: &[],
+        args: &[optional("level", ArgKind::Level)],
+        description: "Show or set the notification level of the current page",
+        build: |args| UserCommand::Notify(args.level(0)),
+    },
+    CommandSpec {
+        name: "queue",
+        aliases: &[],
+        args: &[],
+        description: "List the messages of the current page waiting to be sent",
+        build: |_| UserCommand::Queue(QueueAction::List),
+    },
+    CommandSpec {
+        name: "queue drop",
+        aliases: &[],
+        args: &[optional("index", ArgKind::Number)],
+        description: "Drop the queued message with the given index, or all of them",
+        build: |args| UserCommand::Queue(QueueAction::Drop(args.number(0))),
+    },
+    CommandSpec {
+        name: "queue edit",
+        aliases: &[],
+        args: &[required("index", ArgKind::Number), required("text", ArgKind::Rest)],
+        description: "Replace the text of the queued message with the given index",
+        build: |args| UserCommand::Queue(QueueAction::Edit(args.number(0).unwrap_or(0),
+                                                             args.text(1))),
+    },
+    CommandSpec {
+        name: "help",
+        aliases: &[],
+        args: &[optional("command", ArgKind::Word)],
+        description: "List the commands, or show how to use the given one",
+        build: |args| UserCommand::Help(Some(args.text(0)).filter(|name| !name.is_empty())),
+    },
+    CommandSpec {
+        name: "exit",
+        aliases: &[],
+        args: &[],
+        description: "Exit ostruka",
+        build: |_| UserCommand::Exit,
+    },
+];
+
+/// Usage and description of the command to change the page.
+pub const CHANGE_PAGE: (&str, &str) = (":<n>", "Change to the page with the given number, 0 is the home page");
+
+/// Error of a command that does not follow its specification.
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    UnterminatedQuote,
+    MissingArgument { command: &'static str, arg: &'static str },
+    TooManyArguments { command: &'static str },
+    BadArgument { command: &'static str, arg: &'static str, value: String, expected: &'static str },
+}
+
+impl ParseError {
+    /// Returns the name of the misused command, if any.
+    pub fn command(&self) -> Option<&'static str> {
+        match self {
+            ParseError::UnterminatedQuote => None,
+            ParseError::MissingArgument { command, .. } 
+                | ParseError::TooManyArguments { command } 
+                | ParseError::BadArgument { command, .. } => Some(*command),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnterminatedQuote => write!(f, "Unterminated quote")?,
+            ParseError::MissingArgument { command, arg } =>
+                write!(f, "Missing <{}> for :{}", arg, command)?,
+            ParseError::TooManyArguments { command } =>
+                write!(f, "Too many arguments for :{}", command)?,
+            ParseError::BadArgument { command, arg, value, expected } =>
+                write!(f, "Invalid <{}> for :{}: `{}`, expected {}", arg, command, value, expected)?,
+        }
+
+        // Remind the user how the command is used
+        match self.command().and_then(|name| COMMANDS.iter().find(|spec| spec.name == name)) {
+            Some(spec) => write!(f, ". Usage: {}", usage(spec)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Value of an argument, converted to its kind.
+enum Value {
+    Text(String),
+    Number(usize),
+    Level(NotifyLevel),
+}
+
+/// Values of the arguments given to a command, in the order of its specification.
+/// Optional arguments that were not given are `None`.
+struct Args(Vec<Option<Value>>);
+
+impl Args {
+    fn text(&self, index: usize) -> String {
+        match self.0.get(index) {
+            Some(Some(Value::Text(text))) => text.clone(),
+            _ => String::new(),
+        }
+    }
+
+    fn number(&self, index: usize) -> Option<usize> {
+        match self.0.get(index) {
+            Some(Some(Value::Number(n))) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn level(&self, index: usize) -> Option<NotifyLevel> {
+        match self.0.get(index) {
+            Some(Some(Value::Level(level))) => Some(*level),
+            _ => None,
+        }
+    }
+}
+
+/// Splits a command line in tokens separated by whitespace. A token that starts with a
+/// quote, single or double, ends at the matching quote and can contain whitespace. In
+/// double quotes, a backslash escapes the next character.
+#[derive(Clone)]
+pub struct Tokens<'a> {
+    rest: &'a str, // Text not tokenized yet
+}
+
+impl<'a> Tokens<'a> {
+
+    pub fn new(input: &'a str) -> Tokens<'a> {
+        Tokens { rest: input }
+    }
+
+    /// Returns the text not tokenized yet, without leading whitespace, and ends the
+    /// tokenization.
+    pub fn rest(&mut self) -> &'a str {
+        let rest = self.rest.trim_start();
+        self.rest = "";
+        rest
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<String, ParseError>;
+
+    fn next(&mut self) -> Option<Result<String, ParseError>> {
+        let input = self.rest.trim_start();
+        let mut chars = input.char_indices();
+        let (_, first) = chars.next()?;
+
+        if first != '"' && first != '\'' {
+            let end = input.find(char::is_whitespace).unwrap_or(input.len());
+            self.rest = &input[end..];
+            return Some(Ok(input[..end].to_string()));
+        }
+
+        let mut token = String::new();
+        let mut escaped = false;
+        for (i, c) in chars {
+            if escaped {
+                token.push(c);
+                escaped = false;
+            } else if c == '\\' && first == '"' {
+                escaped = true;
+            } else if c == first {
+                self.rest = &input[i + c.len_utf8()..];
+                return Some(Ok(token));
+            } else {
+                token.push(c);
+            }
+        }
+        self.rest = "";
+        Some(Err(ParseError::UnterminatedQuote))
+    }
+}
+
+/// Parses a line typed by the user. Lines that do not start with `:` are messages,
+/// `:<n>` changes to the page `n` and other commands are looked up in `COMMANDS`.
+/// Unknown command names are returned as `UserCommand::Unknown`.
+pub fn parse_command(input: &str) -> Result<UserCommand, ParseError> {
+    if !input.starts_with(':') {
+        return Ok(UserCommand::Message(input.to_string()));
+    }
+
+    let mut tokens = Tokens::new(&input[1..]);
+    let name = match tokens.next() {
+        Some(name) => name?,
+        None => return Ok(UserCommand::Unknown(input.to_string())),
+    };
+
+    // ChangePage command
+    if let Ok(index) = name.parse() {
+        return match tokens.next() {
+            None => Ok(UserCommand::ChangePage(index)),
+            Some(_) => Ok(UserCommand::Unknown(input.to_string())),
+        };
+    }
+
+    // Look for the command with the most words matching the line, so subcommands
+    // take precedence over their command
+    let mut found: Option<(&CommandSpec, Tokens)> = None;
+    for spec in COMMANDS {
+        let mut words = spec.name.split(' ');
+        if words.next() != Some(name.as_str()) && !spec.aliases.contains(&name.as_str()) {
+            continue;
+        }
+
+        let mut after = tokens.clone();
+        let matched = words.all(|word| match after.next() {
+            Some(Ok(token)) => token == word,
+            _ => false,
+        });
+        let longer = found.as_ref().is_none_or(|(best, _)| word_count(best) < word_count(spec));
+        if matched && longer {
+            found = Some((spec, after));
+        }
+    }
+
+    match found {
+        Some((spec, tokens)) => parse_args(spec, tokens),
+        None => Ok(UserCommand::Unknown(input.to_string())),
+    }
+}
+
+/// Checks the arguments of a command against its specification and builds it.
+fn parse_args(spec: &CommandSpec, mut tokens: Tokens) -> Result<UserCommand, ParseError> {
+    let mut values = vec![];
+
+    for arg in spec.args {
+        let token = match arg.kind {
+            ArgKind::Rest => Some(tokens.rest().to_string()).filter(|rest| !rest.is_empty()),
+            _ => tokens.next().transpose()?,
+        };
+
+        let value = match token {
+            Some(token) => Some(convert(spec, arg, token)?),
+            None if arg.optional => None,
+            None => return Err(ParseError::MissingArgument { command: spec.name, arg: arg.name }),
+        };
+        values.push(value);
+    }
+
+    if tokens.next().is_some() {
+        return Err(ParseError::TooManyArguments { command: spec.name });
+    }
+    Ok((spec.build)(&Args(values)))
+}
+
+/// Converts a token to the kind of the given argument.
+fn convert(spec: &CommandSpec, arg: &ArgSpec, token: String) -> Result<Value, ParseError> {
+    let value = match arg.kind {
+        ArgKind::Word | ArgKind::Rest => return Ok(Value::Text(token)),
+        ArgKind::Number => token.parse().ok().map(Value::Number),
+        ArgKind::Level => token.parse().ok().map(Value::Level),
+    };
+
+    value.ok_or(ParseError::BadArgument {
+        command: spec.name,
+        arg: arg.name,
+        value: token,
+        expected: arg.kind.expected(),
+    })
+}
+
+fn word_count(spec: &CommandSpec) -> usize {
+    spec.name.split(' ').count()
+}
+
+/// Returns the names and aliases of the commands, with the leading `:`.
+pub fn command_names() -> Vec<String> {
+    let mut names: Vec<String> = COMMANDS.iter()
+        .flat_map(|spec| spec.name.split(' ').take(1).chain(spec.aliases.iter().copied()))
+        .map(|name| format!(":{}", name))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Returns the syntax of a command, e.g. `:queue drop [index]`.
+pub fn usage(spec: &CommandSpec) -> String {
+    let mut usage = format!(":{}", spec.name);
+    for arg in spec.args {
+        if arg.optional {
+            usage.push_str(&format!(" [{}]", arg.name));
+        } else {
+            usage.push_str(&format!(" <{}>", arg.name));
+        }
+    }
+    usage
+}
+
+/// Returns the aliases of a command separated by commas, if it has any.
+fn aliases(spec: &CommandSpec) -> Option<String> {
+    if spec.aliases.is_empty() {
+        return None;
+    }
+    let aliases: Vec<String> = spec.aliases.iter().map(|alias| format!(":{}", alias)).collect();
+    Some(aliases.join(", "))
+}
+
+/// Returns the lines of the help. Without a command, every command is listed with its
+/// syntax and description. With a command name or alias, only that command and its
+/// subcommands are described. Returns `None` if the command does not exist.
+pub fn help(command: Option<&str>) -> Option<Vec<String>> {
+    let command = match command {
+        Some(name) => name.trim_start_matches(':'),
+        None => {
+            let mut entries: Vec<(String, &str)> = COMMANDS.iter()
+                .map(|spec| match aliases(spec) {
+                    Some(aliases) => (format!("{} ({})", usage(spec), aliases), spec.description),
+                    None => (usage(spec), spec.description),
+                })
+                .collect();
+            entries.push((CHANGE_PAGE.0.to_string(), CHANGE_PAGE.1));
+
+            let width = entries.iter().map(|(usage, _)| usage.chars().count()).max().unwrap_or(0);
+            let mut lines = vec!["Commands, type :help <command> for details:".to_string()];
+            lines.extend(entries.iter()
+                .map(|(usage, description)| format!("  {:width$}  {}", usage, description, width = width)));
+            return Some(lines);
+        },
+    };
+
+    let mut lines = vec![];
+    for spec in COMMANDS {
+        let first = spec.name.split(' ').next();
+        if first != Some(command) && !spec.aliases.contains(&command) {
+            continue;
+        }
+
+        lines.push(format!("Usage: {}", usage(spec)));
+        if let Some(aliases) = aliases(spec) {
+            lines.push(format!("Aliases: {}", aliases));
+        }
+        lines.push(format!("  {}", spec.description));
+    }
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines)
+    }
+}
@@ -0,0 +1,117 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Makes text received from the network safe to display in the terminal.
+///
+/// - Escape sequences (CSI, OSC, DCS and the rest of `ESC` sequences, both in their
+///   7-bit and 8-bit forms) are removed.
+/// - Other C0 control characters are replaced by their visible Unicode symbol
+///   (e.g. `␍` for a carriage return), and C1 control characters by `�`.
+/// - Bidirectional text overrides, that can be used to spoof lines, are removed.
+/// - Newlines are kept, `\r\n` is converted to `\n` and tabs to spaces.
+pub fn sanitize(text: &str) -> String {
+    let mut clean = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => clean.push('\n'),
+            '\t' => clean.push(' '),
+            '\r' if chars.peek() == Some(&'\n') => (),
+
+            '\x1b' => skip_escape(&mut chars),
+            '\u{9b}' => skip_csi(&mut chars),
+            '\u{90}' | '\u{98}' | '\u{9d}' | '\u{9e}' | '\u{9f}' => skip_string(&mut chars),
+
+            // Bidirectional embeddings, overrides and isolates
+            '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}' => (),
+
+            '\x00'..='\x1f' => clean.push(control_picture(c)),
+            '\x7f' => clean.push('\u{2421}'),
+            '\u{80}'..='\u{9f}' => clean.push('\u{fffd}'),
+            _ => clean.push(c),
+        }
+    }
+    clean
+}
+
+/// Makes text received from the network safe to display in a single row. The text is
+/// sanitized and its newlines are replaced by `␤`, so a message cannot start a row
+/// that looks like another message.
+pub fn sanitize_line(text: &str) -> String {
+    sanitize(text).replace('\n', "\u{2424}")
+}
+
+/// Returns the Unicode symbol that represents the given C0 control character.
+fn control_picture(c: char) -> char {
+    std::char::from_u32(0x2400 + c as u32).unwrap_or('\u{fffd}')
+}
+
+/// Skips the rest of an escape sequence whose `ESC` was already consumed.
+fn skip_escape(chars: &mut Peekable<Chars>) {
+    match chars.peek() {
+        Some('[') => {
+            chars.next();
+            skip_csi(chars);
+        },
+        Some(']') | Some('P') | Some('X') | Some('^') | Some('_') => {
+            chars.next();
+            skip_string(chars);
+        },
+        // Intermediate bytes followed by a final byte, e.g. `ESC ( B`
+        Some('\x20'..='\x2f') => {
+            while let Some('\x20'..='\x2f') = chars.peek() {
+                chars.next();
+            }
+            if let Some('\x30'..='\x7e') = chars.peek() {
+                chars.next();
+            }
+        },
+        // Two character sequences, e.g. `ESC c` resets the terminal
+        Some('\x30'..='\x7e') => {
+            chars.next();
+        },
+        // A lone ESC, nothing else to skip
+        _ => (),
+    }
+}
+
+/// Skips the parameters and the final byte of a CSI sequence.
+fn skip_csi(chars: &mut Peekable<Chars>) {
+    while let Some(&c) = chars.peek() {
+        match c {
+            // Parameter and intermediate bytes
+            '\x20'..='\x3f' => {
+                chars.next();
+            },
+            // Final byte
+            '\x40'..='\x7e' => {
+                chars.next();
+                return;
+            },
+            // Malformed sequence, the rest of the text is processed normally
+            _ => return,
+        }
+    }
+}
+
+/// Skips the content of a string sequence (OSC, DCS, SOS, PM and APC) up to and
+/// including its terminator: BEL, `ESC \` or the 8-bit ST. If there is no
+/// terminator, the rest of the text is skipped.
+fn skip_string(chars: &mut Peekable<Chars>) {
+    while let Some(c) = chars.next() {
+        match c {
+            '\x07' | '\u{9c}' => return,
+            // `ESC \` is the terminator, other sequences also end the string
+            '\x1b' => {
+                if chars.peek() == Some(&'\\') {
+                    chars.next();
+                } else {
+                    skip_escape(chars);
+                }
+                return;
+            },
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use chrono::{DateTime, Local};
+
+/// State of the connection with the server.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ConnState {
+    Connecting,   // Trying to log in
+    Connected,
+    Reconnecting, // The connection was lost, waiting to try again
+}
+
+/// Connection information displayed in the status line of the TUI.
+#[derive(Clone)]
+pub struct Status {
+    pub state: ConnState,
+    pub server: String,
+    pub alias: String,
+    since: Option<DateTime<Local>>, // Time when the current connection was established
+}
+
+impl Status {
+
+    /// Creates a new `Status` in the connecting state.
+    pub fn new(server: &str, alias: &str) -> Status {
+        Status {
+            state: ConnState::Connecting,
+            server: server.to_string(),
+            alias: alias.to_string(),
+            since: None,
+        }
+    }
+
+    /// Changes the state of the connection. The connection time is reset when the
+    /// state changes to connected.
+    pub fn set_state(&mut self, state: ConnState) {
+        if state == ConnState::Connected && self.state != ConnState::Connected {
+            self.since = Some(Local::now());
+        } else if state != ConnState::Connected {
+            self.since = None;
+        }
+        self.state = state;
+    }
+
+    /// Returns the time elapsed since the connection was established, formatted as
+    /// `HH:MM:SS`. If not connected, `None` is returned.
+    pub fn uptime(&self) -> Option<String> {
+        self.since.map(|since| {
+            let secs = Local::now().signed_duration_since(since).num_seconds().max(0);
+            format!("{:02}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+        })
+    }
+}
+
+impl Default for Status {
+    fn default() -> Status {
+        Status::new("", "")
+    }
+}
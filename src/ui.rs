@@ -6,15 +6,21 @@ use tokio::sync::Mutex;
 
 use tui::Terminal;
 use tui::backend::Backend;
-use tui::widgets::{Widget, Block, Borders, Text, Paragraph, List, SelectableList};
+use tui::widgets::{Widget, Block, Borders, Text, Paragraph, List};
 use tui::layout::{Layout, Constraint, Direction, Alignment};
 use tui::style::{Color, Style, Modifier};
 
+use chrono::Local;
+
+use crate::input::InputBuffer;
 use crate::instance::Instance;
+use crate::line::{ChatLine, Delivery, LineKind};
+use crate::status::ConnState;
 
 pub async fn draw_tui<B : Backend>(terminal: &mut Terminal<B>, 
                                    username: &str,
-                                   user_buff: &str, 
+                                   user_buff: &InputBuffer, 
+                                   search: Option<(&str, &str)>,
                                    instance: &Arc<Mutex<Instance>>) -> Result<(), io::Error>{
     
     // Get the chat vector of the current page
@@ -24,18 +30,34 @@ pub async fn draw_tui<B : Backend>(terminal: &mut Terminal<B>,
     };
     // Get a vector with the names of all active pages
     let names = instance.lock().await.names();
+    // Number of unread messages of each page
+    let unread = instance.lock().await.get_unread();
+    // Pages with unseen mentions
+    let mentioned = instance.lock().await.get_mentioned();
     // Current pages index
     let current_index = instance.lock().await.get_current();
     // Get currently online users, if the current page is not a group chat None is returned
     let online_users = instance.lock().await.get_online_users();
+    // State of the connection with the server
+    let status = instance.lock().await.status.clone();
+    // Format of the timestamps, None if they are hidden
+    let timestamp_format = {
+        let instance = instance.lock().await;
+        if instance.show_timestamps {
+            Some(instance.timestamp_format.clone())
+        } else {
+            None
+        }
+    };
 
-    // Create the vertical layout, divide the screen into two sections
+    // Create the vertical layout, divide the screen into three sections
     let v_chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(0)
         .constraints([
-            Constraint::Percentage(95),
-            Constraint::Percentage(5)
+            Constraint::Min(0),    // Chat
+            Constraint::Length(1), // Status line
+            Constraint::Length(2), // Input box, its top border and one line
         ].as_ref())
         .split(terminal.size()?);
 
@@ -50,23 +72,48 @@ pub async fn draw_tui<B : Backend>(terminal: &mut Terminal<B>,
         ].as_ref())
         .split(v_chunks[0]);
 
-    // Calculate the range of chat lines to be displayed in the text box
+    // Get the rows to display from the chat of the current page
+    let mut rows = chat_rows(&chat, timestamp_format.as_deref());
+
+    // Calculate the range of chat rows to be displayed in the text box
     // This method also controlls the scroll value
     let range = instance.lock()
         .await
-        .display_range(h_chunks[1].height as usize);
+        .display_range(h_chunks[1].height as usize, rows.len());
     
     terminal.draw(move |mut f| {
         // Tabs
-        SelectableList::default()
+        let tabs = names.iter()
+            .zip(unread.iter().zip(mentioned.iter()))
+            .enumerate()
+            .map(|(i, (name, (&count, &mention)))| {
+                // The current page is marked and underlined, pages with unread messages 
+                // show the number of messages and are highlighted. Pages with unseen
+                // mentions are marked with an `@`.
+                let marker = if i == current_index { ">" } else { " " };
+                let mut label = if count > 0 {
+                    format!("{} {} ({})", marker, name, count)
+                } else {
+                    format!("{} {}", marker, name)
+                };
+                if mention {
+                    label.push_str(" @");
+                }
+
+                let style = if i == current_index {
+                    Style::default().fg(Color::Magenta).modifier(Modifier::UNDERLINED)
+                } else if mention {
+                    Style::default().fg(Color::LightRed).modifier(Modifier::BOLD)
+                } else if count > 0 {
+                    Style::default().fg(Color::Yellow).modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                Text::styled(label, style)
+            });
+
+        List::new(tabs)
             .block(Block::default().borders(Borders::RIGHT))
-            .items(&names)
-            .select(Some(current_index))
-            .style(Style::default().fg(Color::White))
-            .highlight_style(Style::default()
-                .fg(Color::Magenta)
-                .modifier(Modifier::UNDERLINED))
-            .highlight_symbol(">")
             .render(&mut f, h_chunks[0]);
 
         if let Some(online_list) = online_users {
@@ -77,12 +124,12 @@ pub async fn draw_tui<B : Backend>(terminal: &mut Terminal<B>,
                 .render(&mut f, h_chunks[2]);
         }
         
-        // Get the text vector from the chat of the current page
+        // Get the text vector from the rows of the current page
         let mut text = vec![];
-        chat[range]
-            .iter()
-            .for_each(|txt| {
-                text.push(Text::raw(format!("{}\n", txt)))
+        rows.drain(range)
+            .for_each(|mut row| {
+                row.push(Text::raw("\n"));
+                text.append(&mut row);
             });
         
         // Draw the text box (Paragraph)
@@ -93,18 +140,139 @@ pub async fn draw_tui<B : Backend>(terminal: &mut Terminal<B>,
             .wrap(true)
             .render(&mut f, h_chunks[1]);
 
-        // User input box, includes cursor and the alias of the user
+        // Status line: connection state, server, alias, uptime and current time
+        let (state, state_color) = match status.state {
+            ConnState::Connected => ("connected", Color::Green),
+            ConnState::Connecting => ("connecting", Color::Yellow),
+            ConnState::Reconnecting => ("reconnecting", Color::Red),
+        };
+        let uptime = match status.uptime() {
+            Some(uptime) => format!(" | up {}", uptime),
+            None => String::new(),
+        };
         let text = [
-            Text::styled(format!("({})> ", username), 
+            Text::styled(format!(" ● {}", state), 
                          Style::default()
-                         .fg(Color::LightMagenta)
+                         .fg(state_color)
                          .modifier(Modifier::BOLD)),
-            Text::raw(format!("{}▌", user_buff)),
+            Text::raw(format!(" | {} | {}{} | {}", 
+                              status.server, 
+                              status.alias, 
+                              uptime,
+                              Local::now().format("%H:%M:%S"))),
         ];
+        Paragraph::new(text.iter())
+                .block(Block::default().borders(Borders::NONE))
+                .style(Style::default().fg(Color::White).bg(Color::Black))
+                .render(&mut f, v_chunks[1]);
+
+        // User input box, includes cursor and the alias of the user. The cursor is 
+        // drawn over the grapheme it is on, or as a block at the end of the line. During
+        // a reverse search, the query and the matched entry are shown instead.
+        let text = match search {
+            Some((query, found)) => vec![
+                Text::styled(format!("(reverse-i-search)`{}': ", query), 
+                             Style::default()
+                             .fg(Color::LightMagenta)
+                             .modifier(Modifier::BOLD)),
+                Text::raw(found),
+                Text::raw("▌"),
+            ],
+            None => {
+                let (before, current, after) = user_buff.split_at_cursor();
+                vec![
+                    Text::styled(format!("({})> ", username), 
+                                 Style::default()
+                                 .fg(Color::LightMagenta)
+                                 .modifier(Modifier::BOLD)),
+                    Text::raw(before),
+                    match current {
+                        Some(g) => Text::styled(g, Style::default().modifier(Modifier::REVERSED)),
+                        None => Text::raw("▌"),
+                    },
+                    Text::raw(after),
+                ]
+            },
+        };
         Paragraph::new(text.iter())
                 .block(Block::default().borders(Borders::TOP))
                 .style(Style::default().fg(Color::White))
                 .wrap(true)
-                .render(&mut f, v_chunks[1]);
+                .render(&mut f, v_chunks[2]);
     })
 }
+
+/// Converts the lines of a conversation into the rows displayed in the chat box. Each
+/// row is a vector of styled texts, lines with a multiline body take many rows. If a
+/// timestamp format is given, the time of each line is shown before it. A separator
+/// row is added between consecutive lines of different days.
+fn chat_rows(chat: &[ChatLine], timestamp_format: Option<&str>) -> Vec<Vec<Text<'static>>> {
+    let mut rows = vec![];
+    let mut last_day = None;
+
+    for line in chat {
+        // Day change separator
+        let day = line.time.date_naive();
+        if last_day.is_some_and(|last| last != day) {
+            rows.push(vec![Text::styled(line.time.format("── %A %Y-%m-%d ──").to_string(),
+                                        Style::default().fg(Color::DarkGray))]);
+        }
+        last_day = Some(day);
+
+        let sender = line.sender.as_ref().map(|s| s.as_str()).unwrap_or("");
+
+        // Prefix of the first row and style of the body
+        let (mut prefix, body_style) = match line.kind {
+            LineKind::Own => {
+                let mut prefix = vec![];
+                match line.delivery {
+                    Delivery::Sent => (),
+                    Delivery::Pending => prefix.push(
+                        Text::styled("[pending] ", Style::default().fg(Color::Yellow))),
+                    Delivery::Dropped => prefix.push(
+                        Text::styled("[dropped] ", Style::default().fg(Color::DarkGray))),
+                }
+                prefix.push(Text::styled(format!("({})> ", sender),
+                                         Style::default()
+                                         .fg(Color::LightMagenta)
+                                         .modifier(Modifier::BOLD)));
+                let style = match line.delivery {
+                    Delivery::Sent => Style::default().fg(Color::White),
+                    _ => Style::default().fg(Color::DarkGray),
+                };
+                (prefix, style)
+            },
+            LineKind::Incoming if line.mention => (
+                vec![Text::styled(format!("[{}]: ", sender), 
+                                  Style::default()
+                                  .fg(Color::LightRed)
+                                  .modifier(Modifier::BOLD | Modifier::REVERSED))],
+                Style::default().fg(Color::LightRed).modifier(Modifier::BOLD),
+            ),
+            LineKind::Incoming => (
+                vec![Text::styled(format!("[{}]: ", sender), Style::default().fg(Color::Cyan))],
+                Style::default().fg(Color::White),
+            ),
+            LineKind::System => (vec![], Style::default().fg(Color::White)),
+            LineKind::Error => (
+                vec![Text::styled("[ERR]: ", Style::default().fg(Color::Red).modifier(Modifier::BOLD))],
+                Style::default().fg(Color::Red),
+            ),
+            LineKind::Presence => (vec![], Style::default().fg(Color::DarkGray)),
+        };
+
+        if let Some(format) = timestamp_format {
+            prefix.insert(0, Text::styled(format!("{} ", line.time.format(format)),
+                                          Style::default().fg(Color::DarkGray)));
+        }
+
+        let mut prefix = Some(prefix);
+        for body in line.body.split('\n') {
+            // Only the first row of a line has prefix
+            let mut row = prefix.take().unwrap_or_default();
+            row.push(Text::styled(body.to_string(), body_style));
+            rows.push(row);
+        }
+    }
+    rows
+}
@@ -0,0 +1,161 @@
+use ostruka::commands::{QueueAction, UserCommand};
+use ostruka::notify::NotifyLevel;
+use ostruka::parser::{command_names, help, parse_command, ParseError, Tokens};
+
+fn tokens(input: &str) -> Result<Vec<String>, ParseError> {
+    Tokens::new(input).collect()
+}
+
+#[test]
+fn tokens_are_split_on_whitespace() {
+    assert_eq!(tokens("join  #rust\t now ").unwrap(), vec!["join", "#rust", "now"]);
+    assert_eq!(tokens("   ").unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn quotes_group_words() {
+    assert_eq!(tokens(r#"join "my page" 'single quoted'"#).unwrap(),
+               vec!["join", "my page", "single quoted"]);
+    assert_eq!(tokens(r#""say \"hi\"" 'not \escaped'"#).unwrap(),
+               vec![r#"say "hi""#, r"not \escaped"]);
+    assert_eq!(tokens(r#""""#).unwrap(), vec![""]);
+    // Quotes inside a word are part of it
+    assert_eq!(tokens("don't stop").unwrap(), vec!["don't", "stop"]);
+}
+
+#[test]
+fn unterminated_quotes_are_errors() {
+    assert_eq!(tokens(r#"join "my page"#), Err(ParseError::UnterminatedQuote));
+    assert_eq!(parse_command(":join 'my page"), Err(ParseError::UnterminatedQuote));
+}
+
+#[test]
+fn lines_without_colon_are_messages() {
+    assert_eq!(parse_command("hello there").unwrap(), UserCommand::Message("hello there".to_string()));
+    assert_eq!(parse_command("").unwrap(), UserCommand::Message(String::new()));
+}
+
+#[test]
+fn commands_without_arguments() {
+    assert_eq!(parse_command(":exit").unwrap(), UserCommand::Exit);
+    assert_eq!(parse_command(":close").unwrap(), UserCommand::Close);
+    assert_eq!(parse_command(":q").unwrap(), UserCommand::Close);
+    assert_eq!(parse_command(":ts").unwrap(), UserCommand::ToggleTimestamps);
+    assert_eq!(parse_command(":timestamps ").unwrap(), UserCommand::ToggleTimestamps);
+}
+
+#[test]
+fn extra_arguments_are_errors() {
+    assert_eq!(parse_command(":exit now"), Err(ParseError::TooManyArguments { command: "exit" }));
+    assert_eq!(parse_command(":q 1"), Err(ParseError::TooManyArguments { command: "close" }));
+    assert_eq!(parse_command(":join a b"), Err(ParseError::TooManyArguments { command: "join" }));
+}
+
+#[test]
+fn join_takes_a_page() {
+    assert_eq!(parse_command(":join #rust").unwrap(), UserCommand::Join("#rust".to_string()));
+    assert_eq!(parse_command(":join \"my page\"").unwrap(), UserCommand::Join("my page".to_string()));
+    assert_eq!(parse_command(":join"),
+               Err(ParseError::MissingArgument { command: "join", arg: "page" }));
+}
+
+#[test]
+fn names_must_match_whole_words() {
+    assert_eq!(parse_command(":joinfoo").unwrap(), UserCommand::Unknown(":joinfoo".to_string()));
+    assert_eq!(parse_command(":exited").unwrap(), UserCommand::Unknown(":exited".to_string()));
+    assert_eq!(parse_command(":").unwrap(), UserCommand::Unknown(":".to_string()));
+}
+
+#[test]
+fn numbers_change_the_page() {
+    assert_eq!(parse_command(":2").unwrap(), UserCommand::ChangePage(2));
+    assert_eq!(parse_command(": 0").unwrap(), UserCommand::ChangePage(0));
+    assert_eq!(parse_command(":2 3").unwrap(), UserCommand::Unknown(":2 3".to_string()));
+}
+
+#[test]
+fn notify_takes_an_optional_level() {
+    assert_eq!(parse_command(":notify").unwrap(), UserCommand::Notify(None));
+    assert_eq!(parse_command(":notify mentions").unwrap(),
+               UserCommand::Notify(Some(NotifyLevel::Mentions)));
+    assert_eq!(parse_command(":notify loud"), Err(ParseError::BadArgument {
+        command: "notify",
+        arg: "level",
+        value: "loud".to_string(),
+        expected: "all, mentions or none",
+    }));
+}
+
+#[test]
+fn queue_subcommands() {
+    assert_eq!(parse_command(":queue").unwrap(), UserCommand::Queue(QueueAction::List));
+    assert_eq!(parse_command(":queue drop").unwrap(), UserCommand::Queue(QueueAction::Drop(None)));
+    assert_eq!(parse_command(":queue drop 1").unwrap(),
+               UserCommand::Queue(QueueAction::Drop(Some(1))));
+    assert_eq!(parse_command(":queue drop one"), Err(ParseError::BadArgument {
+        command: "queue drop",
+        arg: "index",
+        value: "one".to_string(),
+        expected: "a number",
+    }));
+    assert_eq!(parse_command(":queue flush"), Err(ParseError::TooManyArguments { command: "queue" }));
+}
+
+#[test]
+fn rest_arguments_are_kept_as_typed() {
+    assert_eq!(parse_command(":queue edit 0 don't  \"quote\" me").unwrap(),
+               UserCommand::Queue(QueueAction::Edit(0, "don't  \"quote\" me".to_string())));
+    assert_eq!(parse_command(":queue edit 0"),
+               Err(ParseError::MissingArgument { command: "queue edit", arg: "text" }));
+    assert_eq!(parse_command(":queue edit"),
+               Err(ParseError::MissingArgument { command: "queue edit", arg: "index" }));
+}
+
+#[test]
+fn errors_describe_the_usage() {
+    assert_eq!(ParseError::MissingArgument { command: "join", arg: "page" }.to_string(),
+               "Missing <page> for :join. Usage: :join <page>");
+    assert_eq!(ParseError::TooManyArguments { command: "exit" }.to_string(),
+               "Too many arguments for :exit. Usage: :exit");
+    assert_eq!(parse_command(":queue drop x").unwrap_err().to_string(),
+               "Invalid <index> for :queue drop: `x`, expected a number. Usage: :queue drop [index]");
+    assert_eq!(ParseError::UnterminatedQuote.to_string(), "Unterminated quote");
+}
+
+#[test]
+fn help_takes_an_optional_command() {
+    assert_eq!(parse_command(":help").unwrap(), UserCommand::Help(None));
+    assert_eq!(parse_command(":help queue").unwrap(), UserCommand::Help(Some("queue".to_string())));
+}
+
+#[test]
+fn help_lists_every_command() {
+    let lines = help(None).unwrap();
+    for usage in &[":join <page>", ":close", ":notify [level]", ":queue edit <index> <text>",
+                   ":help [command]", ":exit", ":<n>"] {
+        assert!(lines.iter().any(|line| line.trim_start().starts_with(usage)), "missing {}", usage);
+    }
+}
+
+#[test]
+fn help_describes_a_command() {
+    let lines = help(Some("q")).unwrap();
+    assert_eq!(lines[0], "Usage: :close");
+    assert_eq!(lines[1], "Aliases: :q");
+
+    // Subcommands are included, the colon is optional
+    let lines = help(Some(":queue")).unwrap();
+    assert!(lines.contains(&"Usage: :queue drop [index]".to_string()));
+    assert!(lines.contains(&"Usage: :queue edit <index> <text>".to_string()));
+
+    assert_eq!(help(Some("nope")), None);
+}
+
+#[test]
+fn command_names_include_aliases() {
+    let names = command_names();
+    for name in &[":join", ":q", ":close", ":ts", ":queue", ":exit"] {
+        assert!(names.contains(&name.to_string()), "missing {}", name);
+    }
+    assert_eq!(names.iter().filter(|name| *name == ":queue").count(), 1);
+}
@@ -0,0 +1,102 @@
+use ostruka::instance::{Instance, Page};
+use ostruka::sanitize::{sanitize, sanitize_line};
+
+#[test]
+fn plain_text_is_unchanged() {
+    assert_eq!(sanitize("hello, world! ñ ü 日本 🦀"), "hello, world! ñ ü 日本 🦀");
+    assert_eq!(sanitize("two\nlines"), "two\nlines");
+}
+
+#[test]
+fn csi_sequences_are_removed() {
+    assert_eq!(sanitize("\x1b[31mred\x1b[0m"), "red");
+    assert_eq!(sanitize("\x1b[2J\x1b[Hcleared"), "cleared");
+    assert_eq!(sanitize("\x1b[?1049hswitch"), "switch");
+    assert_eq!(sanitize("\u{9b}31m8-bit"), "8-bit");
+}
+
+#[test]
+fn osc_sequences_are_removed() {
+    // Set the window title, terminated by BEL and by ST
+    assert_eq!(sanitize("\x1b]0;pwned\x07text"), "text");
+    assert_eq!(sanitize("\x1b]0;pwned\x1b\\text"), "text");
+    // Hyperlink
+    assert_eq!(sanitize("\x1b]8;;http://evil\x07link\x1b]8;;\x07"), "link");
+    // Clipboard write, 8-bit OSC and ST
+    assert_eq!(sanitize("\u{9d}52;c;ZXZpbA==\u{9c}text"), "text");
+    // Unterminated string, the rest is swallowed
+    assert_eq!(sanitize("ok\x1b]0;never ends"), "ok");
+}
+
+#[test]
+fn other_escape_sequences_are_removed() {
+    // Terminal reset, charset selection, DCS and a lone ESC at the end
+    assert_eq!(sanitize("a\x1bcb"), "ab");
+    assert_eq!(sanitize("\x1b(0lqk\x1b(B"), "lqk");
+    assert_eq!(sanitize("\x1bP+q544e\x1b\\x"), "x");
+    assert_eq!(sanitize("end\x1b"), "end");
+}
+
+#[test]
+fn control_characters_are_escaped() {
+    // A carriage return could overwrite the line with a spoofed one
+    assert_eq!(sanitize("harmless\r[admin]: spoofed"), "harmless␍[admin]: spoofed");
+    assert_eq!(sanitize("bell\x07"), "bell␇");
+    assert_eq!(sanitize("back\x08space"), "back␈space");
+    assert_eq!(sanitize("del\x7f"), "del␡");
+    assert_eq!(sanitize("c1\u{85}"), "c1\u{fffd}");
+    assert_eq!(sanitize("crlf\r\nline"), "crlf\nline");
+    assert_eq!(sanitize("tab\there"), "tab here");
+}
+
+#[test]
+fn newlines_are_escaped_in_single_rows() {
+    // A newline could start a row that looks like a message of someone else
+    assert_eq!(sanitize_line("hi\n[admin]: paste your password"), "hi␤[admin]: paste your password");
+    assert_eq!(sanitize_line("crlf\r\nline"), "crlf␤line");
+    assert_eq!(sanitize_line("\x1b[31mred\x1b[0m"), "red");
+}
+
+#[test]
+fn bidi_overrides_are_removed() {
+    assert_eq!(sanitize("file\u{202e}gpj.exe"), "filegpj.exe");
+    assert_eq!(sanitize("\u{2066}isolated\u{2069}"), "isolated");
+}
+
+#[test]
+fn instance_sanitizes_received_messages() {
+    let mut instance = Instance::new();
+    instance.add(Page::new("ostruka".to_string(), vec![])).unwrap();
+
+    let (page, mention) = instance.add_msg("\x1b[1mmallory", "anon", "\x1b]0;title\x07hi\rbye").unwrap();
+    assert_eq!(page, "mallory");
+    assert!(!mention);
+    instance.add_line(Some(0), "\x1b[2Jserver says\x1bc").unwrap();
+
+    // The message created a page for the sender
+    assert_eq!(instance.names(), vec!["ostruka", "mallory"]);
+
+    instance.set_current(1).unwrap();
+    let chat = instance.get_chat().unwrap();
+    assert_eq!(chat[0].sender.as_deref(), Some("mallory"));
+    assert_eq!(chat[0].body, "hi␍bye");
+
+    instance.set_current(0).unwrap();
+    let chat = instance.get_chat().unwrap();
+    assert_eq!(chat[0].body, "server says");
+}
+
+#[test]
+fn instance_escapes_newlines_in_received_messages() {
+    let mut instance = Instance::new();
+    instance.add(Page::new("ostruka".to_string(), vec![])).unwrap();
+
+    let (page, _) = instance.add_msg("mallory\n[admin]", "anon", "hi\n[admin]: paste your password").unwrap();
+    assert_eq!(page, "mallory␤[admin]");
+
+    instance.set_current(1).unwrap();
+    let chat = instance.get_chat().unwrap();
+    assert_eq!(chat.len(), 1);
+    assert_eq!(chat[0].sender.as_deref(), Some("mallory␤[admin]"));
+    assert_eq!(chat[0].body, "hi␤[admin]: paste your password");
+}